@@ -0,0 +1,264 @@
+use std::{
+    future::Future,
+    io::{self, BufRead, Write},
+    path::PathBuf,
+    time::Duration,
+};
+
+use clap::Subcommand;
+
+use lern::{
+    database::{self, known, queue, schedule, Database, Source},
+    dictionary::{self, entry},
+    fsrs::{card::Card, Grade, Parameters},
+    progress::Progress,
+    Result,
+};
+
+use crate::settings::Settings;
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Builds the dictionary from a wiktionary JSONL dump, replacing the current one
+    ImportDictionary {
+        /// The dump, plain or compressed with gzip, bzip2, xz or zip; `-` reads it from stdin
+        path: PathBuf,
+        /// Stop at the first entry that can't be read instead of skipping it
+        #[arg(long)]
+        strict: bool,
+    },
+    /// Builds the frequency table from a word list, most frequent word first
+    ImportFrequency { path: PathBuf },
+    /// Adds the lemmas of a text file to the queue
+    Lemmatize {
+        path: PathBuf,
+        /// Also store the sentences each lemma occurs in
+        #[arg(long)]
+        sentences: bool,
+    },
+    /// Inspects the queue
+    Queue {
+        #[command(subcommand)]
+        command: QueueCommand,
+    },
+    /// Marks a word as known and removes it from the queue
+    Blacklist { word: String },
+    /// Forgets a known word, so it's no longer blacklisted in the queue
+    Forget { word: String },
+    /// Adds a card
+    Add {
+        /// The Russian word, looked up like in the dictionary search, so Latin letters and е for ё
+        /// are fine
+        russian: String,
+        native: String,
+        /// The Russian word with stress marks, looked up in the dictionary if not given
+        #[arg(long)]
+        stressed: Option<String>,
+        /// A summary of the word's grammar, taken from the dictionary if not given
+        #[arg(long)]
+        grammar: Option<String>,
+    },
+    /// Exports all cards to a text file that can be imported into Anki
+    Export { path: PathBuf },
+    /// Reviews all due cards
+    Review,
+}
+
+#[derive(Subcommand)]
+pub enum QueueCommand {
+    /// Prints the next lemmas in the queue, one per line
+    List {
+        /// Number of lemmas to skip
+        #[arg(long, default_value_t = 0)]
+        start: usize,
+        /// Maximum number of lemmas to print
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+    },
+}
+
+pub async fn run(db: Database, settings: Settings, command: Command) -> Result<()> {
+    match command {
+        Command::ImportDictionary { path, strict } => {
+            let source = if path.as_os_str() == "-" {
+                Source::Stdin
+            } else {
+                Source::File(path)
+            };
+            let progress = Progress::new();
+            let job = database::create_dictionary(db, source, !strict, progress.clone());
+            let report = with_progress(job, progress, "entries").await?;
+
+            if let Some(path) = &report.path {
+                eprintln!("skipped {} entries:", report.skipped_total());
+                for (reason, count) in &report.skipped {
+                    eprintln!("{count:>8}  {reason}");
+                }
+                eprintln!("see {} for the line numbers", path.display());
+            }
+
+            Ok(())
+        }
+        Command::ImportFrequency { path } => {
+            let progress = Progress::new();
+            let job = database::create_frequency(db, path, progress.clone());
+            with_progress(job, progress, "words").await
+        }
+        Command::Lemmatize { path, sentences } => {
+            let progress = Progress::new();
+            let job = dictionary::lemmatize_from_file(db, path, sentences, progress.clone());
+            with_progress(job, progress, "words").await
+        }
+        Command::Queue {
+            command: QueueCommand::List { start, limit },
+        } => {
+            let lemmas = queue::get_lemmas_queue(
+                db,
+                start,
+                limit,
+                settings.order_frequency,
+                settings.order_general_frequency,
+                settings.order_first_occurence,
+                settings.word_classes,
+            )
+            .await?;
+
+            for lemma in lemmas {
+                println!("{lemma}");
+            }
+
+            Ok(())
+        }
+        Command::Blacklist { word } => queue::blacklist_lemma(db, word).await,
+        Command::Forget { word } => {
+            if !known::remove(db, word.clone()).await? {
+                eprintln!("{word} isn't a known word");
+            }
+
+            Ok(())
+        }
+        Command::Add {
+            russian,
+            native,
+            stressed,
+            grammar,
+        } => {
+            let entries = if database::check_tables(db.clone()).await?["dictionary"] {
+                database::dictionary::find_entries(
+                    db.clone(),
+                    russian.clone(),
+                    settings.input_scheme,
+                )
+                .await?
+            } else {
+                Vec::new()
+            };
+            // a word typed in Latin letters or with е for ё gets the dictionary's spelling
+            let russian = entries.first().map_or(russian, |entry| entry.word.clone());
+            let stressed =
+                stressed.or_else(|| entry::stressed_spelling(&entries).map(str::to_owned));
+            let grammar = grammar
+                .or_else(|| dictionary::grammar::word_summary(&entries, stressed.as_deref()));
+            let card = Card::new(&native, &russian)
+                .with_stressed(stressed)
+                .with_grammar(grammar);
+            schedule::insert_card(db, card).await
+        }
+        Command::Export { path } => schedule::export(db, path).await,
+        Command::Review => review(db, &settings.scheduler).await,
+    }
+}
+
+/// Shows every due card on the terminal and asks for a grade. Each card is saved right away,
+/// so quitting in between doesn't lose any reviews.
+async fn review(db: Database, parameters: &Parameters) -> Result<()> {
+    let cards = schedule::get_due_cards(db.clone(), now()).await?;
+    if cards.is_empty() {
+        println!("No cards are due.");
+        return Ok(());
+    }
+
+    let mut lines = io::stdin().lock().lines();
+    let total = cards.len();
+
+    for (i, mut card) in cards.into_iter().enumerate() {
+        let russian = card.stressed.as_ref().unwrap_or(&card.russian);
+        println!("\n({}/{total}) {}", i + 1, russian);
+        prompt("Press enter to show the answer")?;
+        if lines.next().transpose()?.is_none() {
+            return Ok(());
+        }
+
+        println!("{}", card.native);
+        if let Some(grammar) = &card.grammar {
+            println!("{grammar}");
+        }
+
+        let grade = loop {
+            prompt("1 again, 2 hard, 3 good, 4 easy, q to quit")?;
+            let Some(line) = lines.next().transpose()? else {
+                return Ok(());
+            };
+
+            match line.trim() {
+                "1" => break Grade::Again,
+                "2" => break Grade::Hard,
+                "3" => break Grade::Good,
+                "4" => break Grade::Easy,
+                "q" => return Ok(()),
+                _ => continue,
+            }
+        };
+
+        if card.is_new() {
+            card.initial_schedule(grade, now(), parameters);
+        } else {
+            card.schedule(grade, now(), parameters);
+        }
+
+        schedule::save_review(db.clone(), card, grade).await?;
+    }
+
+    Ok(())
+}
+
+/// Runs a long job while printing its progress to stderr. Ctrl+C cancels the job.
+async fn with_progress<T>(
+    job: impl Future<Output = Result<T>>,
+    progress: Progress,
+    unit: &'static str,
+) -> Result<T> {
+    let watched = progress.clone();
+    let watcher = tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_millis(500));
+        loop {
+            tokio::select! {
+                _ = interval.tick() => eprint!("\r{}\x1b[K", watched.describe(unit)),
+                _ = tokio::signal::ctrl_c() => {
+                    eprint!("\rcancelling...\x1b[K");
+                    watched.cancel();
+                }
+            }
+        }
+    });
+
+    let result = job.await;
+    watcher.abort();
+    eprintln!("\r{}\x1b[K", progress.describe(unit));
+
+    result
+}
+
+fn prompt(text: &str) -> Result<()> {
+    print!("{text} > ");
+    io::stdout().flush()?;
+
+    Ok(())
+}
+
+fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
@@ -0,0 +1,421 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+use tokio_rusqlite::params;
+
+use crate::{error::Error, fsrs::card::Card, Result};
+
+use super::{profile::Profile, Database};
+
+const BACKUP_VERSION: u32 = 1;
+/// Dictionary backups are large and can be rebuilt, so only the most recent ones are kept.
+const DICTIONARY_BACKUPS: usize = 1;
+
+/// Everything the user created themselves. Nothing in here refers to the dictionary by id,
+/// so a backup can be restored on top of any dictionary build.
+#[derive(Serialize, Deserialize)]
+pub struct Backup {
+    pub version: u32,
+    pub created: u64,
+    #[serde(default)]
+    pub cards: Vec<Card>,
+    /// The log of all reviews of the cards.
+    #[serde(default)]
+    pub reviews: Vec<Review>,
+    #[serde(default)]
+    pub lemmas: Vec<Lemma>,
+    #[serde(default)]
+    pub sentences: Vec<Sentence>,
+    #[serde(default)]
+    pub known_words: Vec<String>,
+    /// The settings file of the profile as it was, `None` if there was none.
+    #[serde(default)]
+    pub settings: Option<serde_json::Value>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Review {
+    pub card_id: usize,
+    pub time: u64,
+    /// The [`Grade`](crate::fsrs::Grade) as a number from 1 to 4.
+    pub grade: u8,
+    /// The memory state of the card after the review.
+    pub stability: f64,
+    pub difficulty: f64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Lemma {
+    pub lemma: String,
+    pub frequency: i64,
+    pub general_frequency: Option<i64>,
+    pub blacklisted: bool,
+    pub first_occurence: i64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Sentence {
+    pub lemma: String,
+    pub sentence: String,
+}
+
+/// Saves all user data of the profile and its settings to a JSON file.
+pub async fn backup(db: Database, path: PathBuf) -> Result<()> {
+    let settings = read_settings(&db.profile.settings_path()).await?;
+    let mut backup = db
+        .conn
+        .call(|conn| {
+            let mut backup = Backup {
+                version: BACKUP_VERSION,
+                created: timestamp(),
+                cards: Vec::new(),
+                reviews: Vec::new(),
+                lemmas: Vec::new(),
+                sentences: Vec::new(),
+                known_words: Vec::new(),
+                settings: None,
+            };
+
+            let mut stmt = conn.prepare(
+                "SELECT id, native, russian, stressed, grammar, due, last_review, stability,
+                    difficulty
+                FROM cards",
+            )?;
+            let rows = stmt.query_map([], |row| {
+                Ok(Card {
+                    id: row.get(0)?,
+                    native: row.get(1)?,
+                    russian: row.get(2)?,
+                    stressed: row.get(3)?,
+                    grammar: row.get(4)?,
+                    due: row.get(5)?,
+                    last_review: row.get(6)?,
+                    stability: row.get(7)?,
+                    difficulty: row.get(8)?,
+                })
+            })?;
+
+            for card in rows {
+                backup.cards.push(card?);
+            }
+
+            let mut stmt = conn.prepare(
+                "SELECT card_id, time, grade, stability, difficulty FROM reviews ORDER BY rowid",
+            )?;
+            let rows = stmt.query_map([], |row| {
+                Ok(Review {
+                    card_id: row.get(0)?,
+                    time: row.get(1)?,
+                    grade: row.get(2)?,
+                    stability: row.get(3)?,
+                    difficulty: row.get(4)?,
+                })
+            })?;
+
+            for review in rows {
+                backup.reviews.push(review?);
+            }
+
+            let mut stmt = conn.prepare(
+                "SELECT lemma, frequency, general_frequency, blacklisted, first_occurence
+                FROM lemmas",
+            )?;
+            let rows = stmt.query_map([], |row| {
+                Ok(Lemma {
+                    lemma: row.get(0)?,
+                    frequency: row.get(1)?,
+                    general_frequency: row.get(2)?,
+                    blacklisted: row.get(3)?,
+                    first_occurence: row.get(4)?,
+                })
+            })?;
+
+            for lemma in rows {
+                backup.lemmas.push(lemma?);
+            }
+
+            let mut stmt = conn.prepare("SELECT lemma, sentence FROM sentences")?;
+            let rows = stmt.query_map([], |row| {
+                Ok(Sentence {
+                    lemma: row.get(0)?,
+                    sentence: row.get(1)?,
+                })
+            })?;
+
+            for sentence in rows {
+                backup.sentences.push(sentence?);
+            }
+
+            let mut stmt = conn.prepare("SELECT word FROM known_words")?;
+            let rows = stmt.query_map([], |row| row.get(0))?;
+
+            for word in rows {
+                backup.known_words.push(word?);
+            }
+
+            Ok(backup)
+        })
+        .await?;
+    backup.settings = settings;
+
+    let buffer = serde_json::to_vec(&backup)?;
+    fs::write(&path, buffer)
+        .await
+        .map_err(|e| Error::WriteFileFailed(path, e))?;
+
+    Ok(())
+}
+
+/// The settings file, `None` if there is none or it's invalid. Settings are read by the app
+/// itself, which falls back to the defaults for an invalid file, so it isn't an error here.
+async fn read_settings(path: &Path) -> Result<Option<serde_json::Value>> {
+    let buffer = match fs::read(path).await {
+        Ok(buffer) => buffer,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(Error::ReadFileFailed(path.to_owned(), e)),
+    };
+
+    match serde_json::from_slice(&buffer) {
+        Ok(settings) => Ok(Some(settings)),
+        Err(e) => {
+            log::warn!("invalid settings aren't backed up: {e}");
+            Ok(None)
+        }
+    }
+}
+
+/// Replaces the settings file with the settings of a backup, through a temporary file so it's
+/// never left half written.
+async fn write_settings(path: &Path, settings: &serde_json::Value) -> Result<()> {
+    let temporary_path = path.with_extension("json.tmp");
+    let buffer = serde_json::to_vec_pretty(settings)?;
+    fs::write(&temporary_path, buffer)
+        .await
+        .map_err(|e| Error::WriteFileFailed(temporary_path.clone(), e))?;
+    fs::rename(&temporary_path, path)
+        .await
+        .map_err(|e| Error::WriteFileFailed(path.to_owned(), e))?;
+
+    Ok(())
+}
+
+/// Saves a backup of all user data to the backup directory of the profile,
+/// before something gets deleted. Returns the path of the backup.
+pub async fn automatic_backup(db: Database) -> Result<PathBuf> {
+    let directory = db.profile.backup_directory();
+    fs::create_dir_all(&directory).await?;
+
+    let path = unique_path(&directory, "user-data", "json");
+    backup(db, path.clone()).await?;
+
+    Ok(path)
+}
+
+/// Moves the current dictionary file into the shared backup directory,
+/// removing older dictionary backups.
+pub(super) fn backup_dictionary(profile: &Profile) -> Result<()> {
+    let dictionary_path = profile.dictionary_path();
+    if !dictionary_path.exists() {
+        return Ok(());
+    }
+
+    let directory = profile.dictionary_backup_directory();
+    std::fs::create_dir_all(&directory)?;
+
+    let path = unique_path(&directory, "dictionary", "db");
+    std::fs::rename(dictionary_path, path)?;
+
+    let mut backups = Vec::new();
+    for entry in std::fs::read_dir(&directory)? {
+        let name = entry?.file_name().to_string_lossy().into_owned();
+        if name.starts_with("dictionary-") && name.ends_with(".db") {
+            backups.push(name);
+        }
+    }
+
+    backups.sort_by_key(|name| {
+        name.trim_start_matches("dictionary-")
+            .trim_end_matches(".db")
+            .parse::<u64>()
+            .unwrap_or(0)
+    });
+
+    for name in backups.iter().rev().skip(DICTIONARY_BACKUPS) {
+        std::fs::remove_file(directory.join(name))?;
+    }
+
+    Ok(())
+}
+
+/// Saves the words of the current frequency table, most frequent first, as a word list in
+/// `directory` that can be imported again. Nothing is written if the table is empty.
+pub(super) fn backup_frequency(conn: &rusqlite::Connection, directory: &Path) -> Result<()> {
+    let mut stmt = conn.prepare(
+        "SELECT words.word FROM main.frequency
+        JOIN words ON words.id = frequency.word_id
+        GROUP BY words.word
+        ORDER BY MIN(frequency.frequency)",
+    )?;
+    let words = stmt
+        .query_map([], |row| row.get::<usize, String>(0))?
+        .collect::<rusqlite::Result<Vec<String>>>()?;
+
+    if words.is_empty() {
+        return Ok(());
+    }
+
+    std::fs::create_dir_all(directory)?;
+    let path = unique_path(directory, "frequency", "txt");
+    std::fs::write(&path, words.join("\n") + "\n")
+        .map_err(|e| Error::WriteFileFailed(path.clone(), e))?;
+    log::info!("saved the previous frequencies to {}", path.display());
+
+    Ok(())
+}
+
+/// Replaces all user data with the contents of the backup, and the settings if the backup has
+/// them. The current data is saved with [`automatic_backup`] first.
+pub async fn restore(db: Database, path: PathBuf) -> Result<()> {
+    let buffer = fs::read(&path)
+        .await
+        .map_err(|e| Error::ReadFileFailed(path, e))?;
+    let mut backup: Backup = serde_json::from_slice(&buffer)?;
+
+    if backup.version > BACKUP_VERSION {
+        return Err(Error::UnsupportedBackupVersion(backup.version));
+    }
+
+    automatic_backup(db.clone()).await?;
+
+    let tables = super::check_tables(db.clone()).await?;
+    let dictionary = tables["dictionary"] && tables["frequency"];
+    let settings = backup.settings.take();
+    let settings_path = db.profile.settings_path();
+
+    db.conn
+        .call(move |conn| {
+            let ta = conn.transaction()?;
+
+            ta.execute_batch(
+                "DELETE FROM reviews;
+            DELETE FROM cards;
+            DELETE FROM sentences;
+            DELETE FROM lemmas;
+            DELETE FROM known_words;",
+            )?;
+
+            let mut card_stmt = ta.prepare(
+                "INSERT INTO cards (
+                id, native, russian, stressed, grammar, due, last_review, stability, difficulty
+            )
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            )?;
+            for card in backup.cards {
+                card_stmt.execute(params![
+                    card.id,
+                    card.native,
+                    card.russian,
+                    card.stressed,
+                    card.grammar,
+                    card.due,
+                    card.last_review,
+                    card.stability,
+                    card.difficulty
+                ])?;
+            }
+
+            let mut review_stmt = ta.prepare(
+                "INSERT INTO reviews (card_id, time, grade, stability, difficulty)
+                VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            for review in backup.reviews {
+                review_stmt.execute(params![
+                    review.card_id,
+                    review.time,
+                    review.grade,
+                    review.stability,
+                    review.difficulty
+                ])?;
+            }
+
+            let mut lemma_stmt = ta.prepare(
+            "INSERT INTO lemmas (lemma, frequency, general_frequency, blacklisted, first_occurence)
+                VALUES (?1, ?2, ?3, ?4, ?5)",
+        )?;
+            for lemma in backup.lemmas {
+                lemma_stmt.execute(params![
+                    lemma.lemma,
+                    lemma.frequency,
+                    lemma.general_frequency,
+                    lemma.blacklisted,
+                    lemma.first_occurence
+                ])?;
+            }
+
+            let mut sentence_stmt =
+                ta.prepare("INSERT INTO sentences (lemma, sentence) VALUES (?1, ?2)")?;
+            for sentence in backup.sentences {
+                sentence_stmt.execute(params![sentence.lemma, sentence.sentence])?;
+            }
+
+            let mut known_stmt =
+                ta.prepare("INSERT OR IGNORE INTO known_words (word) VALUES (?1)")?;
+            for word in backup.known_words {
+                known_stmt.execute([word])?;
+            }
+
+            drop(card_stmt);
+            drop(review_stmt);
+            drop(lemma_stmt);
+            drop(sentence_stmt);
+            drop(known_stmt);
+
+            // general frequencies belong to the dictionary build the backup was made with
+            if dictionary {
+                ta.execute(
+                    "UPDATE lemmas SET general_frequency = (
+                    SELECT MIN(frequency.frequency) FROM frequency
+                    JOIN words ON words.id = frequency.word_id
+                    WHERE words.word = lemmas.lemma
+                )",
+                    (),
+                )?;
+            }
+
+            ta.commit()?;
+
+            Ok(())
+        })
+        .await?;
+
+    if let Some(settings) = settings {
+        write_settings(&settings_path, &settings).await?;
+    }
+
+    Ok(())
+}
+
+/// A path `{prefix}-{milliseconds}.{extension}` in `directory` that does not exist yet,
+/// so backups made in quick succession don't overwrite each other.
+fn unique_path(directory: &Path, prefix: &str, extension: &str) -> PathBuf {
+    let mut millis = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_millis())
+        .unwrap_or(0);
+
+    loop {
+        let path = directory.join(format!("{prefix}-{millis}.{extension}"));
+        if !path.exists() {
+            return path;
+        }
+        millis += 1;
+    }
+}
+
+fn timestamp() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
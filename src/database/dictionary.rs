@@ -1,537 +1,917 @@
-use serde_json::Value;
-use std::collections::HashMap;
-use std::fs::File;
-use std::io::{self, BufRead};
-use std::path::{Path, PathBuf};
+use std::collections::{HashMap, HashSet};
+use std::sync::mpsc::Receiver;
 use tokio_rusqlite::{params, Connection, Transaction};
 
-use crate::dictionary::entry::{Entry, Example, Form, Pronunciation, Sense};
-use crate::dictionary::{self, WordClass};
-use crate::error::Error;
+use crate::dictionary::entry::{
+    Entry, Example, Form, Pronunciation, RelatedTerm, Sense, Synonym, Translation,
+};
+use crate::dictionary::{
+    edit_distance, grammar::Grammar, is_latin, normalize, stress_position, transliterate,
+    InputScheme, WordClass,
+};
+use crate::progress::Progress;
 use crate::Result;
 
-fn read_lines<P>(filename: P) -> Result<io::Lines<io::BufReader<File>>>
-where
-    P: AsRef<Path>,
-{
-    let file = File::open(filename)?;
-    Ok(io::BufReader::new(file).lines())
-}
+use super::{
+    backup, frequency,
+    input::Source,
+    wiktionary::{self, Batch, FormOf, ImportReport, SkippedLine},
+    Database,
+};
+
+/// Version of the dictionary schema, stored in the dictionary's `PRAGMA user_version`.
+/// Dictionaries built with a different version have to be rebuilt, so it's raised once per
+/// release that changes what is stored.
+pub(super) const DICTIONARY_VERSION: usize = 2;
+
+/// Results of a search at most, so common terms don't produce endless lists.
+const SEARCH_LIMIT: usize = 100;
+
+/// Spellings sharing parts with the query that are compared to it when nothing matches exactly.
+const FUZZY_CANDIDATES: usize = 500;
+
+/// Queries shorter than this aren't corrected, too many words are a single edit away from them.
+const MIN_FUZZY_LENGTH: usize = 4;
+
+/// Builds the dictionary in a separate file which replaces the current one once it's complete,
+/// so a failed or cancelled import leaves the previous dictionary untouched.
+pub(super) async fn create_tables(
+    db: Database,
+    wiktionary: Source,
+    lenient: bool,
+    progress: Progress,
+) -> Result<ImportReport> {
+    let dictionary_path = db.profile.dictionary_path();
+    let temporary_path = dictionary_path.with_extension("db.tmp");
+    if temporary_path.exists() {
+        std::fs::remove_file(&temporary_path)?;
+    }
 
-pub async fn create_tables(path_to_wiktionary: PathBuf) -> Result<()> {
-    let conn = Connection::open("./db/database.db").await?;
-
-    conn.call(|conn| {
-        conn.execute_batch(
-            "DROP TABLE IF EXISTS form_tags;
-            DROP TABLE IF EXISTS pronunciation_tags;                
-            DROP TABLE IF EXISTS sense_tags;
-            DROP TABLE IF EXISTS sense_synonyms;
-            DROP TABLE IF EXISTS examples;
-            DROP TABLE IF EXISTS pronunciation;
-            DROP TABLE IF EXISTS senses;
-            DROP TABLE IF EXISTS synonyms;
-            DROP TABLE IF EXISTS forms;
-            DROP TABLE IF EXISTS frequency;
-            DROP TABLE IF EXISTS words;",
-        )?;
+    let batches = wiktionary::parse_file(&wiktionary, lenient, progress.clone())?;
+    let conn = Connection::open(&temporary_path).await?;
 
-        conn.execute_batch(
-            "DROP INDEX IF EXISTS word_index;
-            DROP INDEX IF EXISTS word_form_index;
-            DROP INDEX IF EXISTS form_tag_index;
-            DROP INDEX IF EXISTS sense_index;
-            DROP INDEX IF EXISTS example_index;
-            DROP INDEX IF EXISTS sense_synonym_index;
-            DROP INDEX IF EXISTS pronunciation_index;
-            DROP INDEX IF EXISTS normalized_form_index;",
-        )?;
+    let old_path = dictionary_path.clone();
+    let result = conn
+        .call(move |conn| {
+            // The file is thrown away if anything fails, so it doesn't need to survive a crash.
+            conn.execute_batch(
+                "PRAGMA journal_mode = MEMORY;
+            PRAGMA synchronous = OFF;",
+            )?;
 
-        conn.execute(
-            "CREATE TABLE words (
+            conn.execute(
+                "CREATE TABLE words (
                 id INTEGER PRIMARY KEY,
                 word TEXT NOT NULL,
                 pos TEXT NOT NULL,
                 etymology TEXT,
-                expansion TEXT
+                expansion TEXT,
+                stressed TEXT,
+                stress INTEGER,
+                gender TEXT,
+                animacy TEXT,
+                aspect TEXT,
+                inflection_class TEXT
             )",
-            (),
-        )?;
+                (),
+            )?;
 
-        conn.execute(
-            "CREATE TABLE senses (
+            conn.execute(
+                "CREATE TABLE senses (
                 id INTEGER PRIMARY KEY,
                 word_id INTEGER NOT NULL,
+                parent_id INTEGER,
                 sense TEXT,
+                raw_sense TEXT,
+                qualifier TEXT,
                 relevance INTEGER NOT NULL,
-                FOREIGN KEY(word_id) REFERENCES words(id)
+                FOREIGN KEY(word_id) REFERENCES words(id),
+                FOREIGN KEY(parent_id) REFERENCES senses(id)
             )",
-            (),
-        )?;
+                (),
+            )?;
 
-        conn.execute(
-            "CREATE TABLE examples (
+            conn.execute(
+                "CREATE TABLE examples (
                 sense_id INTEGER NOT NULL,
                 text TEXT NOT NULL,
                 english TEXT,
                 FOREIGN KEY(sense_id) REFERENCES senses(id)
             )",
-            (),
-        )?;
+                (),
+            )?;
 
-        conn.execute(
-            "CREATE TABLE pronunciation (
+            conn.execute(
+                "CREATE TABLE pronunciation (
                 id INTEGER PRIMARY KEY,
                 word_id INTEGER NOT NULL,
                 ipa TEXT NOT NULL,
                 FOREIGN KEY(word_id) REFERENCES words(id)
             )",
-            (),
-        )?;
+                (),
+            )?;
 
-        conn.execute(
-            "CREATE TABLE forms (
+            conn.execute(
+                "CREATE TABLE forms (
                 id INTEGER PRIMARY KEY,
                 word_id INTEGER NOT NULL,
                 form TEXT NOT NULL,
                 normalized_form TEXT NOT NULL,
+                stress INTEGER,
                 FOREIGN KEY(word_id) REFERENCES words(id)
             )",
-            (),
-        )?;
+                (),
+            )?;
 
-        conn.execute(
-            "CREATE TABLE synonyms (
+            conn.execute(
+                "CREATE TABLE synonyms (
                 id INTEGER PRIMARY KEY,
-                synonym TEXT NOT NULL
+                synonym TEXT NOT NULL UNIQUE,
+                word_id INTEGER,
+                FOREIGN KEY(word_id) REFERENCES words(id)
             )",
-            (),
-        )?;
+                (),
+            )?;
 
-        conn.execute(
-            "CREATE TABLE sense_synonyms (
+            conn.execute(
+                "CREATE TABLE sense_synonyms (
                 sense_id INTEGER NOT NULL,
                 synonym_id INTEGER NOT NULL,
                 FOREIGN KEY(sense_id) REFERENCES senses(id),
                 FOREIGN KEY(synonym_id) REFERENCES synonyms(id)
             )",
-            (),
-        )?;
+                (),
+            )?;
 
-        conn.execute(
-            "CREATE TABLE form_tags (
+            conn.execute(
+                "CREATE TABLE form_tags (
                 form_id INTEGER NOT NULL,
                 tag TEXT NOT NULL,
                 FOREIGN KEY(form_id) REFERENCES forms(id)
             )",
-            (),
-        )?;
+                (),
+            )?;
 
-        conn.execute(
-            "CREATE TABLE pronunciation_tags (
+            conn.execute(
+                "CREATE TABLE pronunciation_tags (
                 pronunciation_id INTEGER NOT NULL,
                 tag TEXT NOT NULL,
                 FOREIGN KEY(pronunciation_id) REFERENCES pronunciation(id)
             )",
-            (),
-        )?;
+                (),
+            )?;
 
-        conn.execute(
-            "CREATE TABLE sense_tags (
+            conn.execute(
+                "CREATE TABLE sense_tags (
                 sense_id INTEGER NOT NULL,
                 tag TEXT NOT NULL,
                 FOREIGN KEY(sense_id) REFERENCES senses(id)
             )",
-            (),
-        )?;
+                (),
+            )?;
 
-        conn.execute_batch(
-            "CREATE INDEX word_index ON words(word);
+            conn.execute(
+                "CREATE TABLE related_terms (
+                word_id INTEGER NOT NULL,
+                sense_id INTEGER,
+                relation TEXT NOT NULL,
+                term TEXT NOT NULL,
+                FOREIGN KEY(word_id) REFERENCES words(id),
+                FOREIGN KEY(sense_id) REFERENCES senses(id)
+            )",
+                (),
+            )?;
+
+            conn.execute(
+                "CREATE TABLE translations (
+                word_id INTEGER NOT NULL,
+                language TEXT NOT NULL,
+                code TEXT,
+                translation TEXT NOT NULL,
+                sense TEXT,
+                FOREIGN KEY(word_id) REFERENCES words(id)
+            )",
+                (),
+            )?;
+
+            let start = std::time::Instant::now();
+
+            let ta = conn.transaction()?;
+            let (skipped, form_of) = insert_data(&ta, batches, &progress)
+                .map_err(Into::<tokio_rusqlite::Error>::into)?;
+
+            // Filling the indexes at once is a lot faster than updating them with every insert.
+            ta.execute_batch(
+                "CREATE INDEX word_index ON words(word);
             CREATE INDEX word_form_index ON forms(word_id);
             CREATE INDEX form_tag_index ON form_tags(form_id);
             CREATE INDEX sense_index ON senses(word_id);
             CREATE INDEX example_index ON examples(sense_id);
             CREATE INDEX sense_synonym_index ON sense_synonyms(sense_id);
             CREATE INDEX pronunciation_index ON pronunciation(word_id);
+            CREATE INDEX related_term_word_index ON related_terms(word_id);
+            CREATE INDEX related_term_sense_index ON related_terms(sense_id);
+            CREATE INDEX translation_index ON translations(word_id);
             CREATE INDEX normalized_form_index ON forms(normalized_form);",
-        )?;
+            )?;
+            create_gloss_index(&ta)?;
+            insert_form_of(&ta, form_of, &progress).map_err(Into::<tokio_rusqlite::Error>::into)?;
+            create_search_index(&ta)?;
+
+            // Synonyms with an entry of their own link to it.
+            ta.execute(
+                "UPDATE synonyms SET word_id = (
+                SELECT id FROM words WHERE word = synonym ORDER BY id LIMIT 1
+            )",
+                (),
+            )?;
+            ta.pragma_update(None, "user_version", DICTIONARY_VERSION)?;
+            ta.commit()?;
 
-        let start = std::time::Instant::now();
+            let duration = start.elapsed();
+            log::info!("built dictionary in {duration:?}");
 
-        let mut ta = conn.transaction()?;
-        insert_data(&mut ta, path_to_wiktionary).map_err(Into::<tokio_rusqlite::Error>::into)?;
-        ta.commit()?;
+            if old_path.exists() {
+                conn.execute("ATTACH DATABASE ?1 AS old", [old_path.to_string_lossy()])?;
+                frequency::copy_from_old(conn)?;
+                conn.execute("DETACH DATABASE old", ())?;
+            }
 
-        let duration = start.elapsed();
-        println!("Time elapsed for insertion: {:?}", duration);
+            Ok(skipped)
+        })
+        .await;
+
+    conn.close().await?;
+
+    let skipped = match result {
+        Ok(skipped) => skipped,
+        Err(e) => {
+            std::fs::remove_file(&temporary_path)?;
+            return Err(e.into());
+        }
+    };
+
+    let profile = db.profile.clone();
+    db.replace_dictionary(move || {
+        backup::backup_dictionary(&profile)?;
+        std::fs::rename(temporary_path, dictionary_path)?;
 
         Ok(())
     })
     .await?;
 
-    conn.close().await?;
-    Ok(())
+    let report = ImportReport::write(&skipped, &db.profile.import_report_path())?;
+    if report.skipped_total() > 0 {
+        log::warn!(
+            "skipped {} dictionary entries: {:?}",
+            report.skipped_total(),
+            report.skipped
+        );
+    }
+
+    Ok(report)
 }
 
-fn insert_data(ta: &mut Transaction, path_to_wiktionary: PathBuf) -> Result<()> {
-    let mut word_stmt_expansion =
-        ta.prepare("INSERT INTO words (word, pos, expansion) VALUES (?1, ?2, ?3)")?;
-    let mut word_stmt_etymology_expansion =
-        ta.prepare("INSERT INTO words (word, pos, etymology, expansion) VALUES (?1, ?2, ?3, ?4)")?;
-    let mut word_stmt_etymology =
-        ta.prepare("INSERT INTO words (word, pos, etymology) VALUES (?1, ?2, ?3)")?;
-    let mut word_stmt = ta.prepare("INSERT INTO words (word, pos) VALUES (?1, ?2)")?;
-
-    let mut sense_stmt_gloss =
-        ta.prepare("INSERT INTO senses (word_id, sense, relevance) VALUES (?1, ?2, ?3)")?;
-    let mut sense_stmt = ta.prepare("INSERT INTO senses (word_id, relevance) VALUES (?1, ?2)")?;
+/// Inserts the parsed entries as they arrive, this is the only thread writing to the dictionary.
+/// Returns the lines that were skipped and the forms that can only be added once all lemmas are
+/// known.
+fn insert_data(
+    ta: &Transaction,
+    batches: Receiver<Result<Batch>>,
+    progress: &Progress,
+) -> Result<(Vec<SkippedLine>, Vec<FormOf>)> {
+    let mut skipped = Vec::new();
+    let mut form_of = Vec::new();
+    // The same synonyms appear in many senses, each is stored once.
+    let mut synonym_ids: HashMap<String, i64> = HashMap::new();
+
+    let mut word_stmt = ta.prepare(
+        "INSERT INTO words (
+            word, pos, etymology, expansion, stressed, stress, gender, animacy, aspect,
+            inflection_class
+        )
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+    )?;
+    let mut sense_stmt = ta.prepare(
+        "INSERT INTO senses (word_id, parent_id, sense, raw_sense, qualifier, relevance)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+    )?;
     let mut sense_tag_stmt =
         ta.prepare("INSERT INTO sense_tags (sense_id, tag) VALUES (?1, ?2)")?;
-
-    let mut example_stmt_english =
+    let mut example_stmt =
         ta.prepare("INSERT INTO examples (sense_id, text, english) VALUES (?1, ?2, ?3)")?;
-    let mut example_stmt = ta.prepare("INSERT INTO examples (sense_id, text) VALUES (?1, ?2)")?;
-
     let mut synonym_stmt = ta.prepare("INSERT INTO synonyms (synonym) VALUES (?1)")?;
     let mut sense_synonym_stmt =
         ta.prepare("INSERT INTO sense_synonyms (sense_id, synonym_id) VALUES (?1, ?2)")?;
-
-    let mut form_stmt =
-        ta.prepare("INSERT INTO forms (form, word_id, normalized_form) VALUES (?1, ?2, ?3)")?;
+    let mut form_stmt = ta.prepare(
+        "INSERT INTO forms (form, word_id, normalized_form, stress) VALUES (?1, ?2, ?3, ?4)",
+    )?;
     let mut form_tag_stmt = ta.prepare("INSERT INTO form_tags (form_id, tag) VALUES (?1, ?2)")?;
-
     let mut pronunciation_stmt =
         ta.prepare("INSERT INTO pronunciation (word_id, ipa) VALUES (?1, ?2)")?;
     let mut pronunciation_tag_stmt =
         ta.prepare("INSERT INTO pronunciation_tags (pronunciation_id, tag) VALUES (?1, ?2)")?;
-
-    let lines = read_lines(path_to_wiktionary)?;
-
-    'iteration: for (i, line) in lines.map_while(std::io::Result::ok).enumerate() {
-        let json: serde_json::Value = serde_json::from_str(&line)?;
-
-        let word = json
-            .get("word")
-            .ok_or_else(|| Error::GetValueFailed(json.clone(), i))?;
-        let word = word
-            .as_str()
-            .ok_or_else(|| Error::ValueConversionFailed(word.to_owned(), i))?;
-
-        let pos_value = json
-            .get("pos")
-            .ok_or_else(|| Error::GetValueFailed(json.clone(), i))?;
-        let pos_str = pos_value
-            .as_str()
-            .ok_or_else(|| Error::ValueConversionFailed(pos_value.to_owned(), i))?;
-        let pos = WordClass::from(pos_str);
-
-        let etymology = match json.get("etymology_text") {
-            Some(value) => Some(
-                value
-                    .as_str()
-                    .ok_or_else(|| Error::ValueConversionFailed(value.to_owned(), i))?,
-            ),
-            None => None,
-        };
-
-        if pos == WordClass::Unknown {
-            continue 'iteration;
-        }
-
-        let head_templates = json.get("head_templates");
-        let expansion = {
-            if let Some(head_templates) = head_templates {
-                let head_templates = head_templates
-                    .as_array()
-                    .ok_or_else(|| Error::ValueConversionFailed(head_templates.to_owned(), i))?
-                    .first()
-                    .ok_or_else(|| Error::EmptyJSONArray(i))?;
-
-                head_templates
-                    .get("expansion")
-                    .ok_or_else(|| Error::GetValueFailed(head_templates.to_owned(), i))?
-                    .as_str()
-            } else {
-                None
-            }
-        };
-
-        let json_senses = json
-            .get("senses")
-            .ok_or_else(|| Error::GetValueFailed(json.clone(), i))?;
-        let json_senses = json_senses
-            .as_array()
-            .ok_or_else(|| Error::ValueConversionFailed(json_senses.to_owned(), i))?;
-        let mut senses = Vec::new();
-
-        'senses: for sense in json_senses {
-            if sense.get("form_of").is_some() {
+    let mut related_term_stmt = ta.prepare(
+        "INSERT INTO related_terms (word_id, sense_id, relation, term) VALUES (?1, ?2, ?3, ?4)",
+    )?;
+    let mut translation_stmt = ta.prepare(
+        "INSERT INTO translations (word_id, language, code, translation, sense)
+        VALUES (?1, ?2, ?3, ?4, ?5)",
+    )?;
+
+    for batch in batches {
+        progress.check()?;
+
+        let batch = batch?;
+        skipped.extend(batch.skipped);
+
+        for mut word in batch.words {
+            form_of.append(&mut word.form_of);
+            if !word.is_lemma() {
                 continue;
             }
 
-            if let Some(tags) = sense.get("tags") {
-                for tag in tags
-                    .as_array()
-                    .ok_or_else(|| Error::ValueConversionFailed(tags.to_owned(), i))?
-                {
-                    if tag
-                        .as_str()
-                        .ok_or_else(|| Error::ValueConversionFailed(tag.to_owned(), i))?
-                        == "form-of"
-                    {
-                        continue 'senses;
-                    }
+            word_stmt.execute(params![
+                word.word,
+                word.class.to_string(),
+                word.etymology,
+                word.expansion(),
+                word.stressed,
+                stress_position(word.stressed.as_deref().unwrap_or(&word.word)),
+                word.grammar.gender.map(|gender| gender.to_string()),
+                word.grammar.animacy.map(|animacy| animacy.to_string()),
+                word.grammar.aspect.map(|aspect| aspect.to_string()),
+                word.grammar.inflection_class
+            ])?;
+            let word_id = ta.last_insert_rowid();
+            progress.count_item();
+
+            // A sense whose glosses start with the glosses of another sense refines that one.
+            // Parents that only exist as the start of their children's glosses are added as
+            // senses without further data.
+            let mut parents: HashMap<&[String], i64> = HashMap::new();
+            let mut relevance = 0;
+
+            for sense in &word.senses {
+                let mut parent_id = None;
+                for depth in 1..sense.glosses.len() {
+                    let prefix = &sense.glosses[..depth];
+                    let id = match parents.get(prefix) {
+                        Some(&id) => id,
+                        None => {
+                            sense_stmt.execute(params![
+                                word_id,
+                                parent_id,
+                                prefix[depth - 1],
+                                None::<String>,
+                                None::<String>,
+                                relevance
+                            ])?;
+                            relevance += 1;
+                            let id = ta.last_insert_rowid();
+                            parents.insert(prefix, id);
+                            id
+                        }
+                    };
+                    parent_id = Some(id);
                 }
-            }
 
-            senses.push(sense);
-        }
+                sense_stmt.execute(params![
+                    word_id,
+                    parent_id,
+                    sense.glosses.last(),
+                    sense.raw_glosses.last(),
+                    sense.qualifier,
+                    relevance
+                ])?;
+                relevance += 1;
+                let sense_id = ta.last_insert_rowid();
+                if !sense.glosses.is_empty() {
+                    parents.insert(&sense.glosses, sense_id);
+                }
 
-        if senses.is_empty() {
-            continue 'iteration;
-        }
+                for tag in &sense.tags {
+                    sense_tag_stmt.execute(params![sense_id, tag])?;
+                }
 
-        if let (Some(etymology), Some(expansion)) = (etymology, expansion) {
-            word_stmt_etymology_expansion.execute([
-                word,
-                pos.to_string().as_str(),
-                etymology,
-                expansion,
-            ])?;
-        } else if let Some(etymology) = etymology {
-            word_stmt_etymology.execute([word, pos.to_string().as_str(), etymology])?;
-        } else if let Some(expansion) = expansion {
-            word_stmt_expansion.execute([word, pos.to_string().as_str(), expansion])?;
-        } else {
-            word_stmt.execute([word, pos.to_string().as_str()])?;
-        }
+                for example in &sense.examples {
+                    let Some(text) = &example.text else { continue };
+                    example_stmt.execute(params![sense_id, text, example.english])?;
+                }
 
-        let word_id = ta.last_insert_rowid();
-
-        let senses = json
-            .get("senses")
-            .ok_or_else(|| Error::GetValueFailed(json.clone(), i))?;
-        let senses = senses
-            .as_array()
-            .ok_or_else(|| Error::ValueConversionFailed(senses.to_owned(), i))?;
-        for (i, sense) in senses.iter().enumerate() {
-            let glosses = sense.get("glosses");
-            let gloss = if let Some(glosses) = glosses {
-                let glosses = glosses
-                    .as_array()
-                    .ok_or_else(|| Error::ValueConversionFailed(glosses.to_owned(), i))?;
-                let gloss = glosses.first().ok_or_else(|| Error::EmptyJSONArray(i))?;
-                Some(
-                    gloss
-                        .as_str()
-                        .ok_or_else(|| Error::ValueConversionFailed(gloss.to_owned(), i))?,
-                )
-            } else {
-                None
-            };
+                for synonym in sense
+                    .synonyms
+                    .iter()
+                    .filter(|synonym| !synonym.word.is_empty())
+                {
+                    let synonym_id = match synonym_ids.get(&synonym.word) {
+                        Some(&id) => id,
+                        None => {
+                            synonym_stmt.execute([&synonym.word])?;
+                            let id = ta.last_insert_rowid();
+                            synonym_ids.insert(synonym.word.clone(), id);
+                            id
+                        }
+                    };
+                    sense_synonym_stmt.execute([sense_id, synonym_id])?;
+                }
 
-            if let Some(gloss) = gloss {
-                sense_stmt_gloss.execute(params![word_id, gloss, i])?;
-            } else {
-                sense_stmt.execute(params![word_id, i])?;
+                for (relation, term) in sense.related() {
+                    related_term_stmt.execute(params![
+                        word_id,
+                        sense_id,
+                        relation.to_string(),
+                        term
+                    ])?;
+                }
+
+                // Translations are shown per word, the sense they belong to tells their meaning.
+                for translation in &sense.translations {
+                    if translation.word.is_empty() {
+                        continue;
+                    }
+                    let meaning = translation.sense.as_ref().or(sense.glosses.last());
+                    translation_stmt.execute(params![
+                        word_id,
+                        translation.language,
+                        translation.code,
+                        translation.word,
+                        meaning
+                    ])?;
+                }
             }
 
-            let sense_id = ta.last_insert_rowid();
+            for (relation, term) in word.related() {
+                related_term_stmt.execute(params![
+                    word_id,
+                    None::<i64>,
+                    relation.to_string(),
+                    term
+                ])?;
+            }
 
-            let tags = {
-                if let Some(tags) = sense.get("tags") {
-                    tags.as_array()
-                        .ok_or_else(|| Error::ValueConversionFailed(tags.to_owned(), i))?
-                        .to_owned()
-                } else {
-                    Vec::<Value>::new()
+            for translation in &word.translations {
+                if translation.word.is_empty() {
+                    continue;
                 }
-            };
+                translation_stmt.execute(params![
+                    word_id,
+                    translation.language,
+                    translation.code,
+                    translation.word,
+                    translation.sense
+                ])?;
+            }
 
-            for tag in tags {
-                let tag = tag
-                    .as_str()
-                    .ok_or_else(|| Error::ValueConversionFailed(tag.to_owned(), i))?;
+            for form in &word.forms {
+                form_stmt.execute(params![
+                    form.form,
+                    word_id,
+                    form.normalized,
+                    stress_position(&form.form)
+                ])?;
+                let form_id = ta.last_insert_rowid();
 
-                sense_tag_stmt.execute(params![sense_id, tag])?;
+                for tag in form.tags.iter().flatten() {
+                    form_tag_stmt.execute(params![form_id, tag])?;
+                }
             }
 
-            let examples = {
-                if let Some(examples) = sense.get("examples") {
-                    examples
-                        .as_array()
-                        .ok_or_else(|| Error::ValueConversionFailed(examples.to_owned(), i))?
-                        .to_owned()
-                } else {
-                    Vec::<Value>::new()
-                }
-            };
+            for sound in &word.sounds {
+                let Some(ipa) = &sound.ipa else { continue };
 
-            for example in examples {
-                let text = example
-                    .get("text")
-                    .ok_or_else(|| Error::GetValueFailed(example.to_owned(), i))?;
-                let text = text
-                    .as_str()
-                    .ok_or_else(|| Error::ValueConversionFailed(text.to_owned(), i))?;
-
-                let english = example.get("english");
-                let english = match english {
-                    Some(english) => Some(
-                        english
-                            .as_str()
-                            .ok_or_else(|| Error::ValueConversionFailed(english.to_owned(), i))?,
-                    ),
-                    None => None,
-                };
+                pronunciation_stmt.execute(params![word_id, ipa])?;
+                let pronunciation_id = ta.last_insert_rowid();
 
-                if let Some(english) = english {
-                    example_stmt_english.execute(params![sense_id, text, english])?;
-                } else {
-                    example_stmt.execute(params![sense_id, text])?;
+                for tag in &sound.tags {
+                    pronunciation_tag_stmt.execute(params![pronunciation_id, tag])?;
                 }
             }
+        }
+    }
 
-            if let Some(synonyms) = sense.get("synonyms") {
-                let synonyms = synonyms
-                    .as_array()
-                    .ok_or_else(|| Error::ValueConversionFailed(synonyms.to_owned(), i))?;
+    Ok((skipped, form_of))
+}
 
-                for synonym in synonyms {
-                    let synonym = synonym.get("word").ok_or_else(|| Error::GetValueFailed(synonym.to_owned(), i))?;
-                    let synonym = synonym
-                        .as_str()
-                        .ok_or_else(|| Error::ValueConversionFailed(synonym.to_owned(), i))?;
+/// Indexes the glosses and the translations of the examples for [`search_glosses`]. Stemming
+/// lets "being late" find "to be late".
+fn create_gloss_index(ta: &Transaction) -> rusqlite::Result<()> {
+    ta.execute_batch(
+        "CREATE VIRTUAL TABLE glosses USING fts5(
+            text,
+            word_id UNINDEXED,
+            tokenize = 'porter unicode61'
+        );
+        INSERT INTO glosses (text, word_id)
+            SELECT sense, word_id FROM senses WHERE sense IS NOT NULL;
+        INSERT INTO glosses (text, word_id)
+            SELECT english, word_id FROM examples
+            JOIN senses ON senses.id = examples.sense_id
+            WHERE english IS NOT NULL;",
+    )
+}
 
-                    synonym_stmt.execute([synonym])?;
-                    let synonym_id = ta.last_insert_rowid();
-                    sense_synonym_stmt.execute([sense_id, synonym_id])?;
-                }
+/// Indexes the lemmas and forms in the spelling [`search`] compares them in, see
+/// [`dictionary::normalize`](crate::dictionary::normalize), and their trigrams for finding
+/// spellings close to a misspelled query. The trigrams include the spaces padding each
+/// spelling, so they tell where it starts and ends.
+fn create_search_index(ta: &Transaction) -> rusqlite::Result<()> {
+    ta.execute(
+        "CREATE TABLE search_terms (
+            term TEXT NOT NULL,
+            word_id INTEGER NOT NULL,
+            is_lemma INTEGER NOT NULL,
+            FOREIGN KEY(word_id) REFERENCES words(id)
+        )",
+        (),
+    )?;
+
+    {
+        let mut stmt = ta.prepare(
+            "SELECT id, word, 1 FROM words
+            UNION ALL
+            SELECT word_id, normalized_form, 0 FROM forms
+            ORDER BY 1, 3 DESC",
+        )?;
+        let mut term_stmt =
+            ta.prepare("INSERT INTO search_terms (term, word_id, is_lemma) VALUES (?1, ?2, ?3)")?;
+
+        // Many forms only differ in their stress marks or tags, each spelling is stored once.
+        let mut current_id = None;
+        let mut terms = HashSet::new();
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let word_id: i64 = row.get(0)?;
+            let term = normalize(row.get_ref(1)?.as_str()?);
+            let is_lemma: bool = row.get(2)?;
+
+            if current_id != Some(word_id) {
+                current_id = Some(word_id);
+                terms.clear();
+            }
+            if terms.insert(term.clone()) {
+                term_stmt.execute(params![term, word_id, is_lemma])?;
             }
         }
+    }
 
-        if let Some(forms) = json.get("forms") {
-            let forms = forms
-                .as_array()
-                .ok_or_else(|| Error::ValueConversionFailed(forms.to_owned(), i))?;
-
-            'forms: for form in forms {
-                let word = form
-                    .get("form")
-                    .ok_or_else(|| Error::GetValueFailed(form.to_owned(), i))?;
-                let word = word
-                    .as_str()
-                    .ok_or_else(|| Error::ValueConversionFailed(word.to_owned(), i))?;
-
-                let source = form.get("source");
-                let Some(source) = source else { continue 'forms };
-                let source = source
-                    .as_str()
-                    .ok_or_else(|| Error::ValueConversionFailed(source.to_owned(), i))?;
-                if source != "declension" && source != "conjugation" {
-                    continue 'forms;
-                }
+    ta.execute_batch(
+        "CREATE INDEX search_term_index ON search_terms(term);
+        CREATE VIRTUAL TABLE fuzzy_terms USING fts5(
+            term,
+            tokenize = 'trigram',
+            detail = 'none'
+        );
+        INSERT INTO fuzzy_terms (term) SELECT DISTINCT ' ' || term || ' ' FROM search_terms;",
+    )
+}
 
-                let tags = form.get("tags");
-                let Some(tags) = tags else { continue 'forms };
-                let tags = tags
-                    .as_array()
-                    .ok_or_else(|| Error::ValueConversionFailed(tags.to_owned(), i))?;
-                for tag in tags {
-                    match tag
-                        .as_str()
-                        .ok_or_else(|| Error::ValueConversionFailed(tag.to_owned(), i))?
-                    {
-                        "inflection-template" | "table-tags" | "class" => continue 'forms,
-                        _ => (),
-                    }
-                }
+/// Adds the words documented as "form of" a lemma to the forms of that lemma, so they can be
+/// lemmatized even though they're missing from its inflection table. Lemmas of the same part of
+/// speech are preferred, e.g. a participle is added to the verb rather than to a noun spelled
+/// the same way.
+fn insert_form_of(ta: &Transaction, form_of: Vec<FormOf>, progress: &Progress) -> Result<()> {
+    let mut form_stmt = ta.prepare(
+        "INSERT INTO forms (word_id, form, normalized_form, stress)
+        SELECT id, ?1, ?2, ?5 FROM words
+        WHERE word = ?3
+            AND (pos = ?4 OR NOT EXISTS (SELECT 1 FROM words WHERE word = ?3 AND pos = ?4))
+            AND NOT EXISTS (
+                SELECT 1 FROM forms WHERE word_id = words.id AND normalized_form = ?2
+            )
+        RETURNING id",
+    )?;
+    let mut form_tag_stmt = ta.prepare("INSERT INTO form_tags (form_id, tag) VALUES (?1, ?2)")?;
+
+    let mut added = 0;
+    for form in &form_of {
+        progress.check()?;
+
+        let form_ids = form_stmt
+            .query_map(
+                params![
+                    form.form,
+                    form.normalized,
+                    form.lemma,
+                    form.class.to_string(),
+                    stress_position(&form.form)
+                ],
+                |row| row.get::<usize, i64>(0),
+            )?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        for form_id in &form_ids {
+            for tag in &form.tags {
+                form_tag_stmt.execute(params![form_id, tag])?;
+            }
+        }
+        added += form_ids.len();
+    }
 
-                let normalized = dictionary::remove_accents(word.to_owned())?;
+    log::info!(
+        "added {added} of {} forms documented as form of a lemma",
+        form_of.len()
+    );
 
-                form_stmt.execute(params![word, word_id, &normalized])?;
-                let form_id = ta.last_insert_rowid();
+    Ok(())
+}
 
-                for tag in tags {
-                    let tag = tag
-                        .as_str()
-                        .ok_or_else(|| Error::ValueConversionFailed(tag.to_owned(), i))?;
-                    form_tag_stmt.execute(params![form_id, tag])?;
+/// A word found by [`search`] or [`search_glosses`], with the parts of speech it has entries for.
+#[derive(Debug, Clone)]
+pub struct SearchResult {
+    pub word: String,
+    pub classes: Vec<WordClass>,
+    /// Whether the query is the word itself rather than one of its forms.
+    pub is_lemma: bool,
+    /// The best matching gloss or example translation, for [`search_glosses`].
+    pub gloss: Option<String>,
+    /// The spelling that was found instead of a query with no exact match.
+    pub correction: Option<String>,
+}
+
+/// Words that are `query` or have it as one of their forms, regardless of stress marks and of
+/// ё written as е. A query in Latin letters is transliterated with `scheme` first, see
+/// [`dictionary::transliterate`](crate::dictionary::transliterate). Without an exact match,
+/// the closest spellings are returned, so a typo still finds the word.
+/// Words matching as a lemma come first.
+pub async fn search(db: Database, query: String, scheme: InputScheme) -> Result<Vec<SearchResult>> {
+    let query = query.trim();
+    let query = if is_latin(query) {
+        normalize(&transliterate(query, scheme))
+    } else {
+        normalize(query)
+    };
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let rows = db
+        .conn
+        .call(move |conn| {
+            let mut rows = find_term(conn, &query, None)?;
+            if rows.is_empty() {
+                for term in close_terms(conn, &query)? {
+                    rows.extend(find_term(conn, &term, Some(&term))?);
+                    if rows.len() >= SEARCH_LIMIT {
+                        break;
+                    }
                 }
             }
+
+            Ok(rows)
+        })
+        .await?;
+
+    Ok(merge_homographs(rows))
+}
+
+/// The words with `term` as their normalized lemma or form.
+fn find_term(
+    conn: &rusqlite::Connection,
+    term: &str,
+    correction: Option<&String>,
+) -> rusqlite::Result<Vec<SearchResult>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT words.word, words.pos, MAX(is_lemma) FROM search_terms
+        JOIN words ON words.id = search_terms.word_id
+        WHERE term = ?1
+        GROUP BY words.id
+        ORDER BY 3 DESC, 1
+        LIMIT ?2",
+    )?;
+    let rows = stmt
+        .query_map(params![term, SEARCH_LIMIT], |row| {
+            Ok(SearchResult {
+                word: row.get(0)?,
+                classes: vec![row.get(1)?],
+                is_lemma: row.get(2)?,
+                gloss: None,
+                correction: correction.cloned(),
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    Ok(rows)
+}
+
+/// Spellings of the dictionary at most two edits away from `query`, one for short queries,
+/// the closest first.
+///
+/// An edit changes at most four trigrams of a word, so a spelling close enough has to share
+/// most trigrams with the query. The spellings sharing the most are compared to it.
+fn close_terms(conn: &rusqlite::Connection, query: &str) -> rusqlite::Result<Vec<String>> {
+    let length = query.chars().count();
+    if length < MIN_FUZZY_LENGTH {
+        return Ok(Vec::new());
+    }
+    let max_distance = if length <= 6 { 1 } else { 2 };
+
+    let padded: Vec<char> = format!(" {query} ").chars().collect();
+    let trigrams: HashSet<String> = padded
+        .windows(3)
+        .map(|trigram| trigram.iter().collect())
+        .collect();
+    let min_shared = trigrams.len().saturating_sub(4 * max_distance).max(1);
+
+    let mut shared: HashMap<i64, usize> = HashMap::new();
+    let mut trigram_stmt =
+        conn.prepare_cached("SELECT rowid FROM fuzzy_terms WHERE fuzzy_terms MATCH ?1")?;
+    for trigram in &trigrams {
+        let mut rows = trigram_stmt.query([format!("\"{}\"", trigram.replace('"', "\"\""))])?;
+        while let Some(row) = rows.next()? {
+            *shared.entry(row.get(0)?).or_default() += 1;
         }
+    }
 
-        if let Some(sounds) = json.get("sounds") {
-            let sounds = sounds
-                .as_array()
-                .ok_or_else(|| Error::ValueConversionFailed(sounds.to_owned(), i))?;
-            for sound in sounds {
-                if let Some(ipa) = sound.get("ipa") {
-                    let ipa = ipa
-                        .as_str()
-                        .ok_or_else(|| Error::ValueConversionFailed(ipa.to_owned(), i))?;
-                    pronunciation_stmt.execute(params![word_id, ipa])?;
-                    let pronunciation_id = ta.last_insert_rowid();
-
-                    if let Some(tags) = sound.get("tags") {
-                        let tags = tags
-                            .as_array()
-                            .ok_or_else(|| Error::ValueConversionFailed(tags.to_owned(), i))?;
-                        for tag in tags {
-                            let tag = tag
-                                .as_str()
-                                .ok_or_else(|| Error::ValueConversionFailed(tag.to_owned(), i))?;
-                            pronunciation_tag_stmt.execute(params![pronunciation_id, tag])?;
-                        }
+    let mut candidates: Vec<(i64, usize)> = shared
+        .into_iter()
+        .filter(|(_, count)| *count >= min_shared)
+        .collect();
+    candidates.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    candidates.truncate(FUZZY_CANDIDATES);
+
+    let mut term_stmt = conn.prepare_cached("SELECT term FROM fuzzy_terms WHERE rowid = ?1")?;
+    let mut terms = Vec::new();
+    for (id, _) in candidates {
+        let term: String = term_stmt.query_row([id], |row| row.get(0))?;
+        let term = term.trim();
+        let distance = edit_distance(query, term);
+        if distance <= max_distance {
+            terms.push((term.to_owned(), distance));
+        }
+    }
+    // The sort is stable, so spellings sharing more trigrams stay first.
+    terms.sort_by_key(|(_, distance)| *distance);
+
+    Ok(terms.into_iter().map(|(term, _)| term).collect())
+}
+
+/// Words whose glosses or example translations contain all terms of `query`, like "to be late".
+/// More frequent words come first if the frequency table exists, then better matches.
+pub async fn search_glosses(db: Database, query: String) -> Result<Vec<SearchResult>> {
+    // Quoted terms can't be mistaken for the query syntax of the full-text index.
+    let query = query
+        .split_whitespace()
+        .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ");
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let rows = db
+        .conn
+        .call(move |conn| {
+            let has_frequency = conn
+                .query_row(
+                    "SELECT 1 FROM dictionary.sqlite_schema
+                    WHERE type = 'table' AND name = 'frequency'",
+                    [],
+                    |_| Ok(()),
+                )
+                .is_ok();
+            let frequency = if has_frequency {
+                "(SELECT MIN(frequency) FROM frequency WHERE word_id = matches.word_id)"
+            } else {
+                "NULL"
+            };
+
+            let mut stmt = conn.prepare_cached(&format!(
+                "SELECT words.word, words.pos, matches.text FROM (
+                    SELECT word_id, text, MIN(rank) AS rank FROM glosses
+                    WHERE glosses MATCH ?1
+                    GROUP BY word_id
+                ) AS matches
+                JOIN words ON words.id = matches.word_id
+                ORDER BY {frequency} IS NULL, {frequency}, matches.rank
+                LIMIT ?2"
+            ))?;
+            let rows = stmt
+                .query_map(params![query, SEARCH_LIMIT], |row| {
+                    Ok(SearchResult {
+                        word: row.get(0)?,
+                        classes: vec![row.get(1)?],
+                        is_lemma: true,
+                        gloss: row.get(2)?,
+                        correction: None,
+                    })
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+
+            Ok(rows)
+        })
+        .await?;
+
+    Ok(merge_homographs(rows))
+}
+
+/// Combines the results for words with the same spelling, keeping the order of their first
+/// result.
+fn merge_homographs(rows: Vec<SearchResult>) -> Vec<SearchResult> {
+    let mut results: Vec<SearchResult> = Vec::new();
+    for row in rows {
+        match results.iter_mut().find(|result| result.word == row.word) {
+            Some(result) => {
+                for class in row.classes {
+                    if !result.classes.contains(&class) {
+                        result.classes.push(class);
                     }
                 }
             }
+            None => results.push(row),
         }
     }
 
-    Ok(())
+    results
 }
 
-pub async fn read_entries(word: String) -> Result<Vec<Entry>> {
-    let conn = Connection::open("./db/database.db").await?;
+/// The entries of `word` as [`read_entries`] finds them. Without any, the entries of the word
+/// [`search`] finds for it as a lemma, so a word typed without stress marks, with е for ё or in
+/// Latin letters with `scheme` is still found. Typo corrections aren't used, the word has to be
+/// the same.
+pub async fn find_entries(db: Database, word: String, scheme: InputScheme) -> Result<Vec<Entry>> {
+    let entries = read_entries(db.clone(), word.clone()).await?;
+    if !entries.is_empty() {
+        return Ok(entries);
+    }
+
+    let found = search(db.clone(), word, scheme)
+        .await?
+        .into_iter()
+        .find(|result| result.is_lemma && result.correction.is_none());
+    match found {
+        Some(result) => read_entries(db, result.word).await,
+        None => Ok(entries),
+    }
+}
 
-    let entries = conn
+/// All dictionary entries for a word, one per part of speech and etymology.
+pub async fn read_entries(db: Database, word: String) -> Result<Vec<Entry>> {
+    let entries = db
+        .conn
         .call(|conn| {
             let ta = conn.transaction()?;
-            let mut word_stmt = ta.prepare("SELECT * FROM words WHERE word = ?1")?;
+            let mut word_stmt = ta.prepare_cached(
+                "SELECT id, word, pos, etymology, expansion, stressed, stress, gender, animacy,
+                    aspect, inflection_class
+                FROM words
+                WHERE word = ?1",
+            )?;
 
-            let mut form_stmt = ta.prepare(
-                "SELECT forms.id, form FROM forms
+            let mut form_stmt = ta.prepare_cached(
+                "SELECT forms.id, form, stress FROM forms
                 WHERE word_id = ?1",
             )?;
 
-            let mut form_tag_stmt = ta.prepare("SELECT tag FROM form_tags WHERE form_id = ?1")?;
+            let mut form_tag_stmt =
+                ta.prepare_cached("SELECT tag FROM form_tags WHERE form_id = ?1")?;
 
-            let mut sense_stmt =
-                ta.prepare("SELECT id, sense FROM senses WHERE word_id = ?1 ORDER BY relevance")?;
+            let mut sense_stmt = ta.prepare_cached(
+                "SELECT id, parent_id, sense, raw_sense, qualifier FROM senses
+                WHERE word_id = ?1
+                ORDER BY relevance",
+            )?;
 
             let mut example_stmt =
-                ta.prepare("SELECT text, english FROM examples WHERE sense_id = ?1")?;
+                ta.prepare_cached("SELECT text, english FROM examples WHERE sense_id = ?1")?;
 
-            let mut synonym_stmt = ta.prepare(
-                "SELECT synonym FROM synonyms
+            let mut synonym_stmt = ta.prepare_cached(
+                "SELECT synonym, word_id IS NOT NULL FROM synonyms
                 JOIN sense_synonyms ON synonyms.id = synonym_id
-                JOIN senses ON senses.id = sense_id
                 WHERE sense_id = ?1",
             )?;
 
-            let mut sense_tag_stmt = ta
-                .prepare("SELECT tag FROM sense_tags JOIN senses ON sense_id = id WHERE id = ?1")?;
+            let mut sense_tag_stmt = ta.prepare_cached(
+                "SELECT tag FROM sense_tags JOIN senses ON sense_id = id WHERE id = ?1",
+            )?;
 
             let mut pronunciation_stmt =
-                ta.prepare("SELECT id, ipa FROM pronunciation WHERE word_id = ?1")?;
+                ta.prepare_cached("SELECT id, ipa FROM pronunciation WHERE word_id = ?1")?;
 
-            let mut pronunciation_tag_stmt = ta.prepare(
+            let mut pronunciation_tag_stmt = ta.prepare_cached(
                 "SELECT tag FROM pronunciation_tags
                 JOIN pronunciation ON id = pronunciation_id
                 WHERE id = ?1",
             )?;
 
+            let mut word_related_stmt = ta.prepare_cached(
+                "SELECT relation, term, EXISTS (SELECT 1 FROM words WHERE word = term)
+                FROM related_terms
+                WHERE word_id = ?1 AND sense_id IS NULL",
+            )?;
+
+            let mut sense_related_stmt = ta.prepare_cached(
+                "SELECT relation, term, EXISTS (SELECT 1 FROM words WHERE word = term)
+                FROM related_terms
+                WHERE sense_id = ?1",
+            )?;
+
+            let mut translation_stmt = ta.prepare_cached(
+                "SELECT language, code, translation, sense FROM translations
+                WHERE word_id = ?1
+                ORDER BY language",
+            )?;
+
             let mut entries = Vec::new();
 
             let entry_iter = word_stmt.query_map([word], |row| {
@@ -540,11 +920,21 @@ pub async fn read_entries(word: String) -> Result<Vec<Entry>> {
                 let pos: WordClass = row.get(2)?;
                 let etymology: Option<String> = row.get(3)?;
                 let expansion: Option<String> = row.get(4)?;
-                Ok((id, word, pos, etymology, expansion))
+                let stressed: Option<String> = row.get(5)?;
+                let stress: Option<usize> = row.get(6)?;
+                let grammar = Grammar {
+                    gender: row.get(7)?,
+                    animacy: row.get(8)?,
+                    aspect: row.get(9)?,
+                    inflection_class: row.get(10)?,
+                };
+                Ok((
+                    id, word, pos, etymology, expansion, stressed, stress, grammar,
+                ))
             })?;
 
             for entry in entry_iter {
-                let (id, word, pos, etymology, expansion) = entry?;
+                let (id, word, pos, etymology, expansion, stressed, stress, grammar) = entry?;
 
                 let mut forms = Vec::new();
                 let mut senses = Vec::new();
@@ -553,12 +943,13 @@ pub async fn read_entries(word: String) -> Result<Vec<Entry>> {
                 let form_iter = form_stmt.query_map([id], |row| {
                     let id: i64 = row.get(0)?;
                     let form: String = row.get(1)?;
+                    let stress: Option<usize> = row.get(2)?;
 
-                    Ok((id, form))
+                    Ok((id, form, stress))
                 })?;
 
                 for form in form_iter {
-                    let (id, form) = form?;
+                    let (id, form, stress) = form?;
 
                     let mut tags = Vec::new();
 
@@ -569,18 +960,21 @@ pub async fn read_entries(word: String) -> Result<Vec<Entry>> {
                         tags.push(tag?);
                     }
 
-                    forms.push(Form { form, tags });
+                    forms.push(Form { form, stress, tags });
                 }
 
                 let sense_iter = sense_stmt.query_map([id], |row| {
                     let id: i64 = row.get(0)?;
-                    let sense: String = row.get(1)?;
+                    let parent_id: Option<i64> = row.get(1)?;
+                    let sense: Option<String> = row.get(2)?;
+                    let raw_sense: Option<String> = row.get(3)?;
+                    let qualifier: Option<String> = row.get(4)?;
 
-                    Ok((id, sense))
+                    Ok((id, parent_id, sense, raw_sense, qualifier))
                 })?;
 
                 for sense in sense_iter {
-                    let (id, sense) = sense?;
+                    let (id, parent_id, sense, raw_sense, qualifier) = sense?;
 
                     let mut examples = Vec::new();
                     let mut synonyms = Vec::new();
@@ -598,8 +992,12 @@ pub async fn read_entries(word: String) -> Result<Vec<Entry>> {
                         examples.push(Example { text, english })
                     }
 
-                    let synonym_iter =
-                        synonym_stmt.query_map([id], |row| row.get::<usize, String>(0))?;
+                    let synonym_iter = synonym_stmt.query_map([id], |row| {
+                        Ok(Synonym {
+                            word: row.get(0)?,
+                            has_entry: row.get(1)?,
+                        })
+                    })?;
 
                     for synonym in synonym_iter {
                         synonyms.push(synonym?);
@@ -612,12 +1010,24 @@ pub async fn read_entries(word: String) -> Result<Vec<Entry>> {
                         tags.push(tag?);
                     }
 
-                    senses.push(Sense {
-                        sense,
-                        examples,
-                        synonyms,
-                        tags,
-                    })
+                    let related = sense_related_stmt
+                        .query_map([id], related_term)?
+                        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+                    senses.push((
+                        id,
+                        parent_id,
+                        Sense {
+                            sense: sense.unwrap_or_default(),
+                            raw_sense,
+                            qualifier,
+                            examples,
+                            synonyms,
+                            tags,
+                            related,
+                            children: Vec::new(),
+                        },
+                    ))
                 }
 
                 let pronunciation_iter = pronunciation_stmt.query_map([id], |row| {
@@ -642,14 +1052,34 @@ pub async fn read_entries(word: String) -> Result<Vec<Entry>> {
                     pronunciations.push(Pronunciation { ipa, tags })
                 }
 
+                let related = word_related_stmt
+                    .query_map([id], related_term)?
+                    .collect::<rusqlite::Result<Vec<_>>>()?;
+
+                let translations = translation_stmt
+                    .query_map([id], |row| {
+                        Ok(Translation {
+                            language: row.get(0)?,
+                            code: row.get(1)?,
+                            word: row.get(2)?,
+                            sense: row.get(3)?,
+                        })
+                    })?
+                    .collect::<rusqlite::Result<Vec<_>>>()?;
+
                 entries.push(Entry {
                     word,
                     pos,
                     etymology,
                     expansion,
-                    senses,
+                    stressed,
+                    stress,
+                    grammar,
+                    senses: nest_senses(senses),
                     forms,
                     pronunciations,
+                    related,
+                    translations,
                 })
             }
 
@@ -662,6 +1092,9 @@ pub async fn read_entries(word: String) -> Result<Vec<Entry>> {
             drop(synonym_stmt);
             drop(pronunciation_stmt);
             drop(pronunciation_tag_stmt);
+            drop(word_related_stmt);
+            drop(sense_related_stmt);
+            drop(translation_stmt);
 
             ta.commit()?;
 
@@ -672,94 +1105,170 @@ pub async fn read_entries(word: String) -> Result<Vec<Entry>> {
     Ok(entries)
 }
 
-pub async fn lemmatize_sentences(sentences: Vec<(String, Vec<(String, usize)>)>) -> Result<()> {
-    let conn = Connection::open("./db/database.db").await?;
-    conn.call(|conn| {
-        let ta = conn.transaction()?;
-
-        let max_first_occurence: usize = ta.query_row("SELECT MAX(first_occurence) FROM lemmas", [], |row| row.get(0)).unwrap_or(0);
-
-        let mut insert_lemmas_stmt = ta.prepare(
-            "INSERT INTO lemmas
-                SELECT w.word as lemma, 1 as frequency, frequency.frequency as general_frequency, 0 as blacklisted, ?1 as first_occurence
-                FROM words w
-                JOIN forms ON forms.word_id = w.id
-                JOIN frequency ON w.id = frequency.word_id
-                WHERE normalized_form = ?2
-                GROUP BY w.id
-            ON CONFLICT(lemma) DO UPDATE SET frequency = frequency + 1",
-        )?;
+/// Moves sub-senses into their parents. Parents come first in the order of the senses, so going
+/// backwards, each sense is complete before it's moved.
+fn nest_senses(mut senses: Vec<(i64, Option<i64>, Sense)>) -> Vec<Sense> {
+    let mut top_level = Vec::new();
 
-        let mut insert_sentence_stmt = ta.prepare(
-            "INSERT INTO sentences
-            SELECT lemma, ?1
-            FROM (
-                SELECT word as lemma
-                FROM words
-                JOIN forms ON forms.word_id = words.id
-                JOIN frequency ON words.id = frequency.word_id
-                WHERE normalized_form = ?2
-                GROUP BY words.word
-            ) AS lemmas WHERE (SELECT COUNT(*) FROM sentences WHERE lemma = lemmas.lemma) < 5
-                AND NOT EXISTS (SELECT 1 FROM sentences WHERE lemma = lemmas.lemma AND sentence = ?1)",
-        )?;
+    while let Some((_, parent_id, sense)) = senses.pop() {
+        let parent = parent_id
+            .and_then(|parent_id| senses.iter_mut().rev().find(|(id, _, _)| *id == parent_id));
+
+        match parent {
+            Some((_, _, parent)) => parent.children.insert(0, sense),
+            None => top_level.insert(0, sense),
+        }
+    }
+
+    top_level
+}
+
+fn related_term(row: &rusqlite::Row) -> rusqlite::Result<RelatedTerm> {
+    Ok(RelatedTerm {
+        relation: row.get(0)?,
+        word: row.get(1)?,
+        has_entry: row.get(2)?,
+    })
+}
+
+/// Adds the lemmas of the forms in each sentence to the queue, together with the sentence.
+pub(crate) async fn lemmatize_sentences(
+    db: Database,
+    sentences: Vec<(String, Vec<(String, usize)>)>,
+    progress: Progress,
+) -> Result<()> {
+    progress.set_total(sentences.len() as u64);
+
+    db.conn
+        .call(move |conn| {
+            let ta = conn.transaction()?;
 
-        let start = std::time::Instant::now();
-        for (sentence, forms) in sentences {
-            let size = forms.len();
-            for (form, position) in forms {
-                insert_lemmas_stmt.execute(params![max_first_occurence + position, form])?;
-                if (3..20).contains(&size) {
-                    insert_sentence_stmt.execute(params![sentence, form])?;
+            let max_first_occurence: usize = ta
+                .query_row("SELECT MAX(first_occurence) FROM lemmas", [], |row| {
+                    row.get(0)
+                })
+                .unwrap_or(0);
+
+            let mut insert_lemmas_stmt = ta.prepare_cached(
+                "INSERT INTO lemmas
+                    SELECT w.word as lemma,
+                        1 as frequency,
+                        frequency.frequency as general_frequency,
+                        EXISTS (SELECT 1 FROM known_words k WHERE k.word = w.word) as blacklisted,
+                        ?1 as first_occurence
+                    FROM words w
+                    JOIN forms ON forms.word_id = w.id
+                    JOIN frequency ON w.id = frequency.word_id
+                    WHERE normalized_form = ?2
+                    GROUP BY w.id
+                ON CONFLICT(lemma) DO UPDATE SET frequency = frequency + 1",
+            )?;
+
+            let mut insert_sentence_stmt = ta.prepare_cached(
+                "INSERT INTO sentences
+                SELECT lemma, ?1
+                FROM (
+                    SELECT word as lemma
+                    FROM words
+                    JOIN forms ON forms.word_id = words.id
+                    JOIN frequency ON words.id = frequency.word_id
+                    WHERE normalized_form = ?2
+                    GROUP BY words.word
+                ) AS lemmas
+                WHERE (SELECT COUNT(*) FROM sentences WHERE lemma = lemmas.lemma) < 5
+                    AND NOT EXISTS (
+                        SELECT 1 FROM sentences WHERE lemma = lemmas.lemma AND sentence = ?1
+                    )",
+            )?;
+
+            let start = std::time::Instant::now();
+            for (sentence, forms) in sentences {
+                progress
+                    .check()
+                    .map_err(Into::<tokio_rusqlite::Error>::into)?;
+                progress.advance(1);
+
+                let size = forms.len();
+                for (form, position) in forms {
+                    progress.count_item();
+                    insert_lemmas_stmt.execute(params![max_first_occurence + position, form])?;
+                    if (3..20).contains(&size) {
+                        insert_sentence_stmt.execute(params![sentence, form])?;
+                    }
                 }
             }
-        }
 
-        drop(insert_lemmas_stmt);
-        drop(insert_sentence_stmt);
+            drop(insert_lemmas_stmt);
+            drop(insert_sentence_stmt);
 
-        ta.commit()?;
+            ta.commit()?;
 
-        println!("elapsed: {:?}", start.elapsed());
+            log::info!("lemmatized sentences in {:?}", start.elapsed());
 
-        Ok(())
-    })
-    .await?;
+            Ok(())
+        })
+        .await?;
 
     Ok(())
 }
 
-pub async fn lemmatize(forms: HashMap<String, (usize, usize)>) -> Result<()> {
-    let conn = Connection::open("./db/database.db").await?;
-    conn.call(|conn| {
-        let ta = conn.transaction()?;
-
-        let max_first_occurence: usize = ta.query_row("SELECT MAX(first_occurence) FROM lemmas", [], |row| row.get(0)).unwrap_or(0);
-
-        let mut stmt: rusqlite::Statement = ta.prepare(
-            "INSERT INTO lemmas
-            SELECT w.word, ?1 as frequency, frequency.frequency as general_frequency, 0 as blacklisted, ?2 as first_occurence
-                FROM words w
-                JOIN forms ON forms.word_id = w.id
-                JOIN frequency ON w.id = frequency.word_id
-                WHERE normalized_form = ?3
-                GROUP BY w.id
-            ON CONFLICT(lemma) DO UPDATE SET frequency = frequency + ?1",
-        )?;
+/// Adds the lemmas of the forms to the queue. `forms` maps each form to the number of
+/// occurences and the position of its first occurence.
+pub(crate) async fn lemmatize(
+    db: Database,
+    forms: HashMap<String, (usize, usize)>,
+    progress: Progress,
+) -> Result<()> {
+    progress.set_total(forms.len() as u64);
+
+    db.conn
+        .call(move |conn| {
+            let ta = conn.transaction()?;
 
-        let start = std::time::Instant::now();
-        for (form, (frequency, first_occurence)) in forms {
-            stmt.execute(params![frequency, max_first_occurence + first_occurence, form])?;
-        }
-        drop(stmt);
+            let max_first_occurence: usize = ta
+                .query_row("SELECT MAX(first_occurence) FROM lemmas", [], |row| {
+                    row.get(0)
+                })
+                .unwrap_or(0);
+
+            let mut stmt = ta.prepare_cached(
+                "INSERT INTO lemmas
+                SELECT w.word,
+                    ?1 as frequency,
+                    frequency.frequency as general_frequency,
+                    EXISTS (SELECT 1 FROM known_words k WHERE k.word = w.word) as blacklisted,
+                    ?2 as first_occurence
+                    FROM words w
+                    JOIN forms ON forms.word_id = w.id
+                    JOIN frequency ON w.id = frequency.word_id
+                    WHERE normalized_form = ?3
+                    GROUP BY w.id
+                ON CONFLICT(lemma) DO UPDATE SET frequency = frequency + ?1",
+            )?;
 
-        ta.commit()?;
+            let start = std::time::Instant::now();
+            for (form, (frequency, first_occurence)) in forms {
+                progress
+                    .check()
+                    .map_err(Into::<tokio_rusqlite::Error>::into)?;
+                progress.advance(1);
+                progress.count_item();
+
+                stmt.execute(params![
+                    frequency,
+                    max_first_occurence + first_occurence,
+                    form
+                ])?;
+            }
+            drop(stmt);
 
-        println!("elapsed: {:?}", start.elapsed());
+            ta.commit()?;
 
-        Ok(())
-    })
-    .await?;
+            log::info!("lemmatized text in {:?}", start.elapsed());
+
+            Ok(())
+        })
+        .await?;
 
     Ok(())
 }
@@ -1,58 +1,69 @@
 use std::fs::File;
 use std::io::prelude::*;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use crate::Result;
+use crate::{error::Error, progress::Progress, Result};
 
-use tokio_rusqlite::{params, Connection};
+use super::{backup, Database};
 
-pub async fn create_table<P: AsRef<Path>>(path_to_frequencies: P) -> Result<()> {
-    let conn = Connection::open("./db/database.db").await?;
-
-    conn.call(|conn| {
-        conn.execute_batch(
-            "DROP TABLE IF EXISTS frequency;
-                DROP INDEX IF EXISTS frequency_index;",
-        )?;
-        conn.execute(
-            "CREATE TABLE frequency (
-                    word_id INTEGER NOT NULL,
-                    frequency INTEGER,
-                    FOREIGN KEY(word_id) REFERENCES words(id)
-                )",
-            (),
-        )?;
-
-        conn.execute("CREATE INDEX frequency_index ON frequency(word_id)", ())?;
+use tokio_rusqlite::{params, Connection};
 
-        Ok(())
-    })
-    .await?;
+/// Builds the frequency table on its own connection to the dictionary file,
+/// the shared connection picks up the new table on its next query.
+/// The previous frequencies are saved as a word list in the dictionary backup directory and
+/// only replaced once all new frequencies are inserted.
+pub(super) async fn create_table<P: AsRef<Path>>(
+    db: Database,
+    path_to_frequencies: P,
+    progress: Progress,
+) -> Result<()> {
+    let conn = Connection::open(db.profile.dictionary_path()).await?;
+    let backup_directory = db.profile.dictionary_backup_directory();
 
     let start = std::time::Instant::now();
 
-    insert_frequencies(&conn, path_to_frequencies).await?;
+    insert_frequencies(&conn, path_to_frequencies, backup_directory, progress).await?;
 
     let duration = start.elapsed();
-    println!("Time elapsed for insertion: {:?}", duration);
+    log::info!("inserted frequencies in {duration:?}");
 
     Ok(())
 }
 
-async fn insert_frequencies<P: AsRef<Path>>(conn: &Connection, file: P) -> Result<()> {
-    let mut file = File::open(file)?;
+async fn insert_frequencies<P: AsRef<Path>>(
+    conn: &Connection,
+    file: P,
+    backup_directory: PathBuf,
+    progress: Progress,
+) -> Result<()> {
+    let path = file.as_ref();
     let mut buffer = String::new();
-    file.read_to_string(&mut buffer)?;
+    File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut buffer))
+        .map_err(|e| Error::ReadFileFailed(path.to_path_buf(), e))?;
 
     conn.call(move |conn| {
-        let frequencies = buffer.split_whitespace();
+        let ta = conn.transaction()?;
+        ensure_schema(&ta)?;
+        backup::backup_frequency(&ta, &backup_directory)
+            .map_err(Into::<tokio_rusqlite::Error>::into)?;
+        ta.execute("DELETE FROM main.frequency", ())?;
 
-        let mut select_stmt = conn.prepare("SELECT id FROM words WHERE word = ?1")?;
-        let mut frequency_stmt = conn.prepare("INSERT INTO frequency VALUES (?1, ?2)")?;
+        let frequencies: Vec<&str> = buffer.split_whitespace().collect();
+        progress.set_total(frequencies.len() as u64);
+
+        let mut select_stmt = ta.prepare("SELECT id FROM words WHERE word = ?1")?;
+        let mut frequency_stmt = ta.prepare("INSERT INTO frequency VALUES (?1, ?2)")?;
 
         let mut word_ids = Vec::<(i64, usize)>::new();
 
-        for (i, word) in frequencies.enumerate() {
+        for (i, word) in frequencies.into_iter().enumerate() {
+            progress
+                .check()
+                .map_err(Into::<tokio_rusqlite::Error>::into)?;
+            progress.advance(1);
+            progress.count_item();
+
             let word_iter = select_stmt.query_map(params![word], |row| row.get(0))?;
 
             for word_id in word_iter {
@@ -65,9 +76,63 @@ async fn insert_frequencies<P: AsRef<Path>>(conn: &Connection, file: P) -> Resul
             frequency_stmt.execute(params![word_id, i as i64])?;
         }
 
+        drop(select_stmt);
+        drop(frequency_stmt);
+
+        ta.commit()?;
+
         Ok(())
     })
     .await?;
 
     Ok(())
 }
+
+/// Creates the frequency table unless it exists.
+pub(super) fn ensure_schema(conn: &rusqlite::Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS main.frequency (
+                word_id INTEGER NOT NULL,
+                frequency INTEGER,
+                FOREIGN KEY(word_id) REFERENCES words(id)
+            )",
+        (),
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS main.frequency_index ON frequency(word_id)",
+        (),
+    )?;
+
+    Ok(())
+}
+
+/// Copies the frequencies of a previous dictionary attached as `old`.
+/// Word ids change with every build, so words are matched by their text.
+pub(super) fn copy_from_old(conn: &rusqlite::Connection) -> rusqlite::Result<()> {
+    let exists = conn
+        .query_row(
+            "SELECT name FROM old.sqlite_schema WHERE type = 'table' AND name = 'frequency'",
+            [],
+            |row| row.get::<usize, String>(0),
+        )
+        .is_ok();
+
+    if !exists {
+        return Ok(());
+    }
+
+    ensure_schema(conn)?;
+
+    conn.execute(
+        "INSERT INTO main.frequency
+        SELECT main.words.id, MIN(old.frequency.frequency)
+        FROM old.frequency
+        JOIN old.words ON old.words.id = old.frequency.word_id
+        JOIN main.words ON main.words.word = old.words.word
+        GROUP BY main.words.id",
+        (),
+    )?;
+
+    Ok(())
+}
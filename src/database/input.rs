@@ -0,0 +1,146 @@
+//! Opening wiktionary dumps, which may be compressed or come from stdin.
+
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Read},
+    path::PathBuf,
+};
+
+use bzip2::read::MultiBzDecoder;
+use flate2::read::MultiGzDecoder;
+use xz2::read::XzDecoder;
+
+use crate::{error::Error, progress::Progress, Result};
+
+/// Where a dump is read from.
+#[derive(Debug, Clone)]
+pub enum Source {
+    File(PathBuf),
+    Stdin,
+}
+
+impl Source {
+    /// The path to name in error messages.
+    fn name(&self) -> PathBuf {
+        match self {
+            Source::File(path) => path.clone(),
+            Source::Stdin => PathBuf::from("<stdin>"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Plain,
+    Gzip,
+    Bzip2,
+    Xz,
+    Zip,
+}
+
+impl Format {
+    /// Recognizes the format by its first bytes rather than the file extension, so compressed
+    /// dumps can be piped in as well.
+    fn detect(start: &[u8]) -> Format {
+        if start.starts_with(&[0x1f, 0x8b]) {
+            Format::Gzip
+        } else if start.starts_with(b"BZh") {
+            Format::Bzip2
+        } else if start.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0]) {
+            Format::Xz
+        } else if start.starts_with(b"PK\x03\x04") {
+            Format::Zip
+        } else {
+            Format::Plain
+        }
+    }
+}
+
+/// Counts the bytes read from the source as progress, i.e. before decompression.
+struct Counting<R> {
+    inner: R,
+    progress: Progress,
+}
+
+impl<R: Read> Read for Counting<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.progress.advance(read as u64);
+        Ok(read)
+    }
+}
+
+/// An opened dump whose format is known.
+pub(super) struct Input {
+    name: PathBuf,
+    format: Format,
+    reader: BufReader<Counting<Box<dyn Read + Send>>>,
+}
+
+impl Input {
+    /// Opens the dump and sets the total of `progress` to the file size, which stays unknown
+    /// for stdin.
+    pub fn open(source: &Source, progress: Progress) -> Result<Input> {
+        let name = source.name();
+        let inner: Box<dyn Read + Send> = match source {
+            Source::File(path) => {
+                let file = File::open(path).map_err(|e| Error::ReadFileFailed(name.clone(), e))?;
+                let size = file
+                    .metadata()
+                    .map_err(|e| Error::ReadFileFailed(name.clone(), e))?
+                    .len();
+                progress.set_total(size);
+                Box::new(file)
+            }
+            Source::Stdin => Box::new(io::stdin()),
+        };
+
+        let mut reader = BufReader::new(Counting { inner, progress });
+        let start = reader
+            .fill_buf()
+            .map_err(|e| Error::ReadFileFailed(name.clone(), e))?;
+        let format = Format::detect(start);
+
+        Ok(Input {
+            name,
+            format,
+            reader,
+        })
+    }
+
+    pub fn name(&self) -> &PathBuf {
+        &self.name
+    }
+
+    /// Calls `read` with the decompressed dump, streaming without temporary files.
+    /// Of a zip archive, the first `.jsonl` file is read.
+    pub fn read<T>(mut self, read: impl FnOnce(&mut dyn BufRead) -> T) -> Result<T> {
+        let result = match self.format {
+            Format::Plain => read(&mut self.reader),
+            Format::Gzip => read(&mut BufReader::new(MultiGzDecoder::new(self.reader))),
+            Format::Bzip2 => read(&mut BufReader::new(MultiBzDecoder::new(self.reader))),
+            Format::Xz => read(&mut BufReader::new(XzDecoder::new_multi_decoder(
+                self.reader,
+            ))),
+            Format::Zip => {
+                let entry = loop {
+                    match zip::read::read_zipfile_from_stream(&mut self.reader) {
+                        Ok(Some(entry)) if entry.name().ends_with(".jsonl") => break entry,
+                        Ok(Some(_)) => (),
+                        Ok(None) => {
+                            let e = io::Error::new(
+                                io::ErrorKind::InvalidData,
+                                "the archive contains no .jsonl file",
+                            );
+                            return Err(Error::ReadFileFailed(self.name, e));
+                        }
+                        Err(e) => return Err(Error::ReadFileFailed(self.name, e.into())),
+                    }
+                };
+                read(&mut BufReader::new(entry))
+            }
+        };
+
+        Ok(result)
+    }
+}
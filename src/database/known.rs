@@ -0,0 +1,182 @@
+use std::path::PathBuf;
+
+use tokio::fs;
+use tokio_rusqlite::params;
+
+use crate::{dictionary, error::Error, Result};
+
+use super::Database;
+
+/// Imports a word list with one word per line. Words and inflected forms are matched against the
+/// dictionary regardless of case and resolved to their lemmas, words that can't be found are
+/// stored as they are.
+/// Returns the number of newly known words.
+pub async fn import(db: Database, path: PathBuf) -> Result<usize> {
+    let text = fs::read_to_string(&path)
+        .await
+        .map_err(|e| Error::ReadFileFailed(path, e))?;
+
+    let mut words = Vec::new();
+    for line in text.lines() {
+        let word = line.trim();
+        if word.is_empty() || word.starts_with('#') {
+            continue;
+        }
+
+        words.push(dictionary::remove_accents(word.to_owned())?);
+    }
+
+    let count = db
+        .conn
+        .call(move |conn| {
+            let ta = conn.transaction()?;
+
+            let mut lemma_stmt = ta.prepare("SELECT word FROM words WHERE word = ?1 LIMIT 1")?;
+            let mut form_stmt = ta.prepare(
+                "SELECT DISTINCT word FROM words
+                JOIN forms ON forms.word_id = words.id
+                WHERE normalized_form = ?1",
+            )?;
+            let mut insert_stmt =
+                ta.prepare("INSERT OR IGNORE INTO known_words (word) VALUES (?1)")?;
+
+            let mut count = 0;
+
+            for word in words {
+                let spellings = spellings(&word);
+
+                let mut lemmas: Vec<String> = Vec::new();
+                for spelling in &spellings {
+                    lemmas = lemma_stmt
+                        .query_map([spelling], |row| row.get(0))?
+                        .collect::<rusqlite::Result<_>>()?;
+                    if !lemmas.is_empty() {
+                        break;
+                    }
+                }
+
+                if lemmas.is_empty() {
+                    for spelling in &spellings {
+                        lemmas = form_stmt
+                            .query_map([spelling], |row| row.get(0))?
+                            .collect::<rusqlite::Result<_>>()?;
+                        if !lemmas.is_empty() {
+                            break;
+                        }
+                    }
+                }
+
+                if lemmas.is_empty() {
+                    lemmas.push(word);
+                }
+
+                for lemma in lemmas {
+                    count += insert_stmt.execute([lemma])?;
+                }
+            }
+
+            drop(lemma_stmt);
+            drop(form_stmt);
+            drop(insert_stmt);
+
+            ta.execute(
+                "UPDATE lemmas
+                    SET blacklisted = 1
+                    WHERE lemma IN (SELECT word FROM known_words)",
+                (),
+            )?;
+
+            ta.commit()?;
+
+            Ok(count)
+        })
+        .await?;
+
+    Ok(count)
+}
+
+/// Exports all known and blacklisted lemmas as a word list with one word per line.
+pub async fn export(db: Database, path: PathBuf) -> Result<()> {
+    let buffer = db
+        .conn
+        .call(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT word FROM known_words
+                UNION
+                SELECT lemma FROM lemmas WHERE blacklisted = 1
+                ORDER BY 1",
+            )?;
+            let rows = stmt.query_map([], |row| row.get::<usize, String>(0))?;
+
+            let mut buffer = String::new();
+            for word in rows {
+                buffer += &word?;
+                buffer.push('\n');
+            }
+
+            Ok(buffer)
+        })
+        .await?;
+
+    fs::write(&path, buffer)
+        .await
+        .map_err(|e| Error::WriteFileFailed(path, e))?;
+
+    Ok(())
+}
+
+/// Forgets a known word, so it's no longer blacklisted in the queue.
+/// The word is matched regardless of case. Returns whether the word was known.
+pub async fn remove(db: Database, word: String) -> Result<bool> {
+    let word = dictionary::remove_accents(word.trim().to_owned())?;
+
+    let removed = db
+        .conn
+        .call(move |conn| {
+            let ta = conn.transaction()?;
+
+            let mut removed = 0;
+            for spelling in spellings(&word) {
+                removed += ta.execute("DELETE FROM known_words WHERE word = ?1", [&spelling])?;
+                ta.execute(
+                    "UPDATE lemmas SET blacklisted = 0 WHERE lemma = ?1",
+                    [&spelling],
+                )?;
+            }
+
+            ta.commit()?;
+
+            Ok(removed > 0)
+        })
+        .await?;
+
+    Ok(removed)
+}
+
+/// The spellings a word of a word list may have in the dictionary: as written, in lowercase and
+/// capitalized, because SQLite only compares ASCII letters case-insensitively.
+fn spellings(word: &str) -> Vec<String> {
+    let lowercase = word.to_lowercase();
+    let mut chars = lowercase.chars();
+    let capitalized = chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default();
+
+    let mut spellings = vec![word.to_owned()];
+    for spelling in [lowercase, capitalized] {
+        if !spellings.contains(&spelling) {
+            spellings.push(spelling);
+        }
+    }
+
+    spellings
+}
+
+/// Marks a lemma as known, independent of whether it's currently in the queue.
+pub(super) fn insert(conn: &rusqlite::Connection, lemma: &str) -> rusqlite::Result<()> {
+    conn.prepare_cached("INSERT OR IGNORE INTO known_words (word) VALUES (?1)")?
+        .execute(params![lemma])?;
+
+    Ok(())
+}
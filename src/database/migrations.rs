@@ -0,0 +1,102 @@
+use rusqlite::{Connection, DatabaseName, Transaction};
+
+use crate::{error::Error, Result};
+
+type Migration = fn(&Transaction) -> rusqlite::Result<()>;
+
+/// Migration `i` brings the user database from schema version `i` to `i + 1`.
+/// The version is stored in `PRAGMA user_version`. Never change a migration once it's released,
+/// append a new one instead.
+const MIGRATIONS: &[Migration] = &[initial, stressed_cards, grammar_cards, last_review, reviews];
+
+/// Runs all migrations the user database hasn't seen yet, each in its own transaction.
+pub(super) fn migrate(conn: &mut Connection) -> Result<()> {
+    let version: usize =
+        conn.pragma_query_value(Some(DatabaseName::Main), "user_version", |row| row.get(0))?;
+
+    if version > MIGRATIONS.len() {
+        return Err(Error::UnsupportedSchemaVersion(version));
+    }
+
+    for (i, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let ta = conn.transaction()?;
+        migration(&ta)?;
+        ta.pragma_update(Some(DatabaseName::Main), "user_version", i + 1)?;
+        ta.commit()?;
+    }
+
+    Ok(())
+}
+
+/// The schema as it was before versioning, so existing tables are simply adopted.
+fn initial(ta: &Transaction) -> rusqlite::Result<()> {
+    ta.execute_batch(
+        "CREATE TABLE IF NOT EXISTS main.cards (
+            id INTEGER PRIMARY KEY,
+            native TEXT NOT NULL,
+            russian TEXT NOT NULL,
+            due INTEGER NOT NULL,
+            stability REAL NOT NULL,
+            difficulty REAL NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS main.lemmas (
+            lemma TEXT PRIMARY KEY,
+            frequency INTEGER NOT NULL,
+            general_frequency INTEGER,
+            blacklisted INTEGER NOT NULL CHECK (blacklisted IN (0, 1)),
+            first_occurence INTEGER NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS main.sentences (
+            lemma TEXT NOT NULL,
+            sentence TEXT NOT NULL,
+            FOREIGN KEY(lemma) REFERENCES lemmas(lemma) ON DELETE CASCADE
+        );
+        CREATE INDEX IF NOT EXISTS main.sentence_index ON sentences(lemma);
+        CREATE TABLE IF NOT EXISTS main.known_words (
+            word TEXT PRIMARY KEY
+        );",
+    )
+}
+
+/// Adds the stressed word to the cards. Cards from the queue used to get it as their Russian
+/// side, which is kept as it is so they still match cards already exported to Anki.
+fn stressed_cards(ta: &Transaction) -> rusqlite::Result<()> {
+    ta.execute_batch(
+        "ALTER TABLE main.cards ADD COLUMN stressed TEXT;
+        UPDATE main.cards SET stressed = russian WHERE instr(russian, char(769)) > 0;",
+    )
+}
+
+/// Adds the summary of the word's grammar to the cards.
+fn grammar_cards(ta: &Transaction) -> rusqlite::Result<()> {
+    ta.execute_batch("ALTER TABLE main.cards ADD COLUMN grammar TEXT;")
+}
+
+/// Stores when each card was last reviewed. For cards reviewed before, it's only estimated from
+/// their due date and stability, assuming they were due their stability in whole days later.
+fn last_review(ta: &Transaction) -> rusqlite::Result<()> {
+    ta.execute_batch(
+        "ALTER TABLE main.cards ADD COLUMN last_review INTEGER;
+        UPDATE main.cards
+            SET last_review = MAX(
+                0,
+                due - 86400 * MAX(1, MIN(36500, CAST(ROUND(stability) AS INTEGER)))
+            )
+            WHERE stability > 0;",
+    )
+}
+
+/// Keeps a log of all reviews with the grade and the memory state of the card afterwards.
+fn reviews(ta: &Transaction) -> rusqlite::Result<()> {
+    ta.execute_batch(
+        "CREATE TABLE main.reviews (
+            card_id INTEGER NOT NULL,
+            time INTEGER NOT NULL,
+            grade INTEGER NOT NULL CHECK (grade BETWEEN 1 AND 4),
+            stability REAL NOT NULL,
+            difficulty REAL NOT NULL,
+            FOREIGN KEY(card_id) REFERENCES cards(id) ON DELETE CASCADE
+        );
+        CREATE INDEX main.review_index ON reviews(card_id);",
+    )
+}
@@ -1,14 +1,55 @@
-use std::{collections::{HashMap, HashSet}, path::PathBuf};
+//! Storage of the dictionary and the user data of a profile.
+//!
+//! All functions take a [`Database`], which is cheap to clone.
 
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use profile::Profile;
+use rusqlite::DatabaseName;
 use tokio::fs;
 use tokio_rusqlite::Connection;
 
-use crate::Result;
+use crate::{progress::Progress, Result};
 
+pub mod backup;
 pub mod dictionary;
-pub mod frequency;
+mod frequency;
+mod input;
+pub mod known;
+mod migrations;
+pub mod profile;
 pub mod queue;
 pub mod schedule;
+mod wiktionary;
+
+pub use input::Source;
+pub use wiktionary::ImportReport;
+
+/// Tables built from the wiktionary dump, parents before the tables referencing them.
+const DICTIONARY_TABLES: [&str; 16] = [
+    "words",
+    "senses",
+    "examples",
+    "pronunciation",
+    "forms",
+    "synonyms",
+    "sense_synonyms",
+    "form_tags",
+    "pronunciation_tags",
+    "sense_tags",
+    "related_terms",
+    "translations",
+    "glosses",
+    "search_terms",
+    "fuzzy_terms",
+    "frequency",
+];
+
+/// Reading a dictionary entry alone uses about a dozen statements.
+const STATEMENT_CACHE_CAPACITY: usize = 64;
 
 async fn init(conn: &Connection) -> tokio_rusqlite::Result<()> {
     conn.call(|conn| {
@@ -24,83 +65,251 @@ async fn init(conn: &Connection) -> tokio_rusqlite::Result<()> {
     .await
 }
 
-pub async fn create_schedule() -> Result<()> {
-    fs::create_dir_all("./db").await?;
+/// The user database of a profile, with the dictionary attached as `dictionary`.
+/// Dictionary tables are then available under their plain names.
+///
+/// It's opened once and carried around by the app. Clones share the same connection,
+/// so cloning it for every request is cheap.
+#[derive(Clone)]
+pub struct Database {
+    conn: Connection,
+    profile: Profile,
+}
 
-    let mut conn = Connection::open("./db/database.db").await?;
+impl std::fmt::Debug for Database {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Database")
+            .field("profile", &self.profile)
+            .finish_non_exhaustive()
+    }
+}
 
-    init(&conn).await?;
+impl Database {
+    /// Opens the user database of the profile, creating and migrating it if necessary.
+    pub async fn open(profile: Profile) -> Result<Database> {
+        fs::create_dir_all(profile.directory()).await?;
 
-    schedule::create_table(&mut conn).await?;
+        let conn = Connection::open(profile.database_path()).await?;
 
-    Ok(())
-}
+        init(&conn).await?;
+
+        let dictionary_path = profile.dictionary_path();
+        conn.call(move |conn| {
+            conn.set_prepared_statement_cache_capacity(STATEMENT_CACHE_CAPACITY);
+            attach_dictionary(conn, &dictionary_path)?;
+            move_dictionary_tables(conn)?;
+            migrations::migrate(conn).map_err(Into::<tokio_rusqlite::Error>::into)?;
+
+            Ok(())
+        })
+        .await?;
+
+        Ok(Database { conn, profile })
+    }
 
-pub async fn create_queue(keep_blacklist: bool) -> Result<()> {
-    fs::create_dir_all("./db").await?;
+    /// The profile this database belongs to.
+    pub fn profile(&self) -> &Profile {
+        &self.profile
+    }
 
-    let mut conn = Connection::open("./db/database.db").await?;
+    /// Detaches the dictionary while `replace` swaps the dictionary file and attaches it again
+    /// afterwards, also when `replace` fails.
+    async fn replace_dictionary<F>(&self, replace: F) -> Result<()>
+    where
+        F: FnOnce() -> Result<()> + Send + 'static,
+    {
+        let dictionary_path = self.profile.dictionary_path();
 
-    init(&conn).await?;
+        self.conn
+            .call(move |conn| {
+                // cached statements would keep the old file open
+                conn.flush_prepared_statement_cache();
+                conn.execute("DETACH DATABASE dictionary", ())?;
 
-    queue::create_table(&mut conn, keep_blacklist).await?;
+                let replaced = replace();
+                attach_dictionary(conn, &dictionary_path)?;
+                replaced.map_err(Into::<tokio_rusqlite::Error>::into)?;
+
+                Ok(())
+            })
+            .await?;
+
+        Ok(())
+    }
+}
+
+fn attach_dictionary(conn: &rusqlite::Connection, path: &Path) -> rusqlite::Result<()> {
+    conn.execute("ATTACH DATABASE ?1 AS dictionary", [path.to_string_lossy()])?;
 
     Ok(())
 }
 
-pub async fn create_dictionary(wiktionary_path: PathBuf) -> Result<()> {
-    fs::create_dir_all("./db").await?;
+/// Older versions kept the dictionary inside of the user database.
+/// Moves these tables into the dictionary file, as long as it doesn't contain a dictionary yet.
+fn move_dictionary_tables(conn: &mut rusqlite::Connection) -> rusqlite::Result<()> {
+    let legacy_exists = conn
+        .query_row(
+            "SELECT name FROM main.sqlite_schema WHERE type = 'table' AND name = 'words'",
+            [],
+            |row| row.get::<usize, String>(0),
+        )
+        .is_ok();
+    let dictionary_exists = conn
+        .query_row(
+            "SELECT name FROM dictionary.sqlite_schema WHERE type = 'table' AND name = 'words'",
+            [],
+            |row| row.get::<usize, String>(0),
+        )
+        .is_ok();
+
+    if !legacy_exists {
+        return Ok(());
+    }
+
+    let ta = conn.transaction()?;
+
+    if !dictionary_exists {
+        for table in DICTIONARY_TABLES {
+            let sql: Option<String> = ta
+                .query_row(
+                    "SELECT sql FROM main.sqlite_schema WHERE type = 'table' AND name = ?1",
+                    [table],
+                    |row| row.get(0),
+                )
+                .ok();
+            let Some(sql) = sql else { continue };
+
+            ta.execute(
+                &sql.replacen("CREATE TABLE ", "CREATE TABLE dictionary.", 1),
+                (),
+            )?;
+            ta.execute(
+                &format!("INSERT INTO dictionary.{table} SELECT * FROM main.{table}"),
+                (),
+            )?;
+        }
 
-    let conn = Connection::open("./db/database.db").await?;
+        let mut stmt = ta.prepare(
+            "SELECT sql FROM main.sqlite_schema
+            WHERE type = 'index' AND sql IS NOT NULL AND tbl_name IN (
+                'words', 'senses', 'examples', 'pronunciation', 'forms', 'synonyms',
+                'sense_synonyms', 'form_tags', 'pronunciation_tags', 'sense_tags', 'frequency'
+            )",
+        )?;
+        let indices = stmt
+            .query_map([], |row| row.get::<usize, String>(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        drop(stmt);
+
+        for sql in indices {
+            ta.execute(
+                &sql.replacen("CREATE INDEX ", "CREATE INDEX dictionary.", 1),
+                (),
+            )?;
+        }
+
+        // the legacy tables are exactly the first version of the dictionary schema
+        ta.pragma_update(
+            Some(DatabaseName::Attached("dictionary")),
+            "user_version",
+            1,
+        )?;
+    }
 
-    init(&conn).await?;
+    for table in DICTIONARY_TABLES.iter().rev() {
+        ta.execute(&format!("DROP TABLE IF EXISTS main.{table}"), ())?;
+    }
 
-    dictionary::create_tables(wiktionary_path).await?;
+    ta.commit()?;
 
     Ok(())
 }
 
-pub async fn create_frequency(frequency_path: PathBuf) -> Result<()> {
-    fs::create_dir_all("./db").await?;
+/// Deletes all cards after saving a backup. Returns the path of the backup.
+pub async fn reset_schedule(mut db: Database) -> Result<PathBuf> {
+    let backup = backup::automatic_backup(db.clone()).await?;
 
-    let conn = Connection::open("./db/database.db").await?;
+    schedule::reset_table(&mut db.conn).await?;
 
-    init(&conn).await?;
+    Ok(backup)
+}
 
-    frequency::create_table(frequency_path).await?;
+/// Empties the queue after saving a backup. Returns the path of the backup.
+pub async fn reset_queue(mut db: Database, keep_blacklist: bool) -> Result<PathBuf> {
+    let backup = backup::automatic_backup(db.clone()).await?;
 
-    Ok(())
+    queue::reset_table(&mut db.conn, keep_blacklist).await?;
+
+    Ok(backup)
 }
 
-pub async fn check_tables() -> Result<HashMap<String, bool>> {
-    let conn = Connection::open("./db/database.db").await?;
-
-    let map = conn.call(|conn| {
-        let mut map: HashMap<String, bool> = [
-            (String::from("dictionary"), false),
-            (String::from("frequency"), false),
-            (String::from("cards"), false),
-            (String::from("lemmas"), false),
-        ].into_iter().collect();
-
-        let mut dictionary_tables = HashSet::from([
-            "words", "senses", "examples", "forms", "examples", "pronunciation", "synonyms", "sense_synonyms", "form_tags", "pronunciation_tags", "sense_tags",
-        ]);
-
-        let mut stmt = conn.prepare("SELECT name FROM sqlite_schema WHERE type = 'table'")?;
-        let rows = stmt.query_map([], |row| row.get(0))?;
-        for table in rows {
-            let table: String = table?;
-            dictionary_tables.remove(table.as_str());
-            map.entry(table).and_modify(|e| *e = true);
-        }
+/// Builds the dictionary from a wiktionary JSONL dump, replacing the current one. The dump may be
+/// compressed with gzip, bzip2 or xz, or packed in a zip archive.
+/// Frequencies of the current dictionary are carried over. Progress is counted in bytes of the
+/// dump as read, the count is the number of entries. With `lenient`, entries that can't be read are
+/// skipped and listed in the returned report instead of failing the import.
+pub async fn create_dictionary(
+    db: Database,
+    wiktionary: Source,
+    lenient: bool,
+    progress: Progress,
+) -> Result<ImportReport> {
+    fs::create_dir_all(db.profile.data_dir()).await?;
+
+    dictionary::create_tables(db, wiktionary, lenient, progress).await
+}
 
-        if dictionary_tables.is_empty() {
-            map.entry(String::from("dictionary")).and_modify(|e| *e = true);
-        }
+/// Builds the frequency table from a word list with the most frequent word first.
+pub async fn create_frequency(
+    db: Database,
+    frequency_path: PathBuf,
+    progress: Progress,
+) -> Result<()> {
+    fs::create_dir_all(db.profile.data_dir()).await?;
 
-        Ok(map)
-    }).await?;
+    frequency::create_table(db, frequency_path, progress).await?;
+
+    Ok(())
+}
+
+/// Checks whether an up-to-date dictionary and the frequency table exist.
+pub async fn check_tables(db: Database) -> Result<HashMap<String, bool>> {
+    let map = db
+        .conn
+        .call(|conn| {
+            let mut map: HashMap<String, bool> = [
+                (String::from("dictionary"), false),
+                (String::from("frequency"), false),
+            ]
+            .into_iter()
+            .collect();
+
+            let mut dictionary_tables: HashSet<&str> = DICTIONARY_TABLES.into_iter().collect();
+            dictionary_tables.remove("frequency");
+
+            let mut stmt =
+                conn.prepare("SELECT name FROM dictionary.sqlite_schema WHERE type = 'table'")?;
+            let rows = stmt.query_map([], |row| row.get(0))?;
+            for table in rows {
+                let table: String = table?;
+                dictionary_tables.remove(table.as_str());
+                map.entry(table).and_modify(|e| *e = true);
+            }
+
+            let version: usize = conn.pragma_query_value(
+                Some(DatabaseName::Attached("dictionary")),
+                "user_version",
+                |row| row.get(0),
+            )?;
+
+            if dictionary_tables.is_empty() && version == dictionary::DICTIONARY_VERSION {
+                map.entry(String::from("dictionary"))
+                    .and_modify(|e| *e = true);
+            }
+
+            Ok(map)
+        })
+        .await?;
 
     Ok(map)
-}
\ No newline at end of file
+}
@@ -0,0 +1,136 @@
+use std::path::{Path, PathBuf};
+
+use directories::ProjectDirs;
+
+use crate::{error::Error, Result};
+
+/// The profile used when none is chosen.
+pub const DEFAULT_PROFILE: &str = "default";
+/// Older versions kept everything in `./db`, relative to the working directory.
+const LEGACY_DIRECTORY: &str = "./db";
+
+/// A learner with their own cards, queue and known words.
+/// All profiles in a data directory share the dictionary.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Profile {
+    data_dir: PathBuf,
+    name: String,
+}
+
+impl Profile {
+    /// Profile names become directory names, so only letters, digits, `-` and `_` are allowed.
+    pub fn new(data_dir: PathBuf, name: String) -> Result<Profile> {
+        let valid = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+
+        if !valid {
+            return Err(Error::InvalidProfileName(name));
+        }
+
+        Ok(Profile { data_dir, name })
+    }
+
+    /// Name of the profile, also the name of its directory.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Directory containing the shared dictionary and all profiles.
+    pub fn data_dir(&self) -> &Path {
+        &self.data_dir
+    }
+
+    /// Directory of this profile, `profiles/<name>` in the data directory.
+    pub fn directory(&self) -> PathBuf {
+        self.data_dir.join("profiles").join(&self.name)
+    }
+
+    /// The user database with cards, queue and known words.
+    pub fn database_path(&self) -> PathBuf {
+        self.directory().join("database.db")
+    }
+
+    /// Settings of the app for this profile.
+    pub fn settings_path(&self) -> PathBuf {
+        self.directory().join("settings.json")
+    }
+
+    /// Where automatic backups of the user database are saved.
+    pub fn backup_directory(&self) -> PathBuf {
+        self.directory().join("backups")
+    }
+
+    /// The dictionary file, shared by all profiles in the data directory.
+    pub fn dictionary_path(&self) -> PathBuf {
+        self.data_dir.join("dictionary.db")
+    }
+
+    /// Where the previous dictionary is kept when it's rebuilt.
+    pub fn dictionary_backup_directory(&self) -> PathBuf {
+        self.data_dir.join("backups")
+    }
+
+    /// Lists the entries that were skipped when the dictionary was last built.
+    pub fn import_report_path(&self) -> PathBuf {
+        self.data_dir.join("import-report.txt")
+    }
+}
+
+/// `$XDG_DATA_HOME/lern` on Linux and the platform's equivalent elsewhere.
+pub fn default_data_dir() -> PathBuf {
+    ProjectDirs::from("", "", "lern")
+        .map(|dirs| dirs.data_dir().to_path_buf())
+        .unwrap_or_else(|| PathBuf::from(LEGACY_DIRECTORY))
+}
+
+/// Names of all profiles in the data directory, sorted.
+pub fn list(data_dir: &Path) -> Result<Vec<String>> {
+    let directory = data_dir.join("profiles");
+    if !directory.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut profiles = Vec::new();
+    for entry in std::fs::read_dir(directory)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            profiles.push(entry.file_name().to_string_lossy().into_owned());
+        }
+    }
+
+    profiles.sort();
+
+    Ok(profiles)
+}
+
+/// Copies the data of older versions from `./db` into the default profile,
+/// as long as the profile doesn't have a database yet. `./db` itself is left as it is.
+/// Returns whether anything was copied.
+pub fn adopt_legacy_files(profile: &Profile) -> Result<bool> {
+    let legacy_database = Path::new(LEGACY_DIRECTORY).join("database.db");
+    let legacy_dictionary = Path::new(LEGACY_DIRECTORY).join("dictionary.db");
+
+    if profile.name != DEFAULT_PROFILE
+        || profile.database_path().exists()
+        || !legacy_database.exists()
+    {
+        return Ok(false);
+    }
+
+    std::fs::create_dir_all(profile.directory())?;
+
+    // VACUUM INTO makes a consistent copy, including anything still in the write-ahead log
+    let conn = rusqlite::Connection::open(&legacy_database)?;
+    conn.execute(
+        "VACUUM INTO ?1",
+        [profile.database_path().to_string_lossy()],
+    )?;
+
+    if legacy_dictionary.exists() && !profile.dictionary_path().exists() {
+        std::fs::copy(legacy_dictionary, profile.dictionary_path())?;
+    }
+
+    Ok(true)
+}
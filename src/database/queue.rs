@@ -1,47 +1,30 @@
 use std::collections::HashSet;
 
-use tokio_rusqlite::Connection;
+use tokio_rusqlite::{params, Connection};
 
 use crate::{
     dictionary::{self, WordClass},
     Result,
 };
 
-pub async fn create_table(conn: &mut Connection, keep_blacklist: bool) -> Result<()> {
+use super::{known, Database};
+
+/// Empties the queue, optionally keeping blacklisted lemmas. Without them, known words are
+/// forgotten as well, since lemmatizing would blacklist them again.
+pub(super) async fn reset_table(conn: &mut Connection, keep_blacklist: bool) -> Result<()> {
     conn.call(move |conn| {
-        let row: rusqlite::Result<String> = conn.query_row(
-            "SELECT name FROM sqlite_schema WHERE type='table' AND name='lemmas'",
-            [],
-            |row| row.get(0),
-        );
+        let ta = conn.transaction()?;
 
-        let table_exists = row.is_ok();
+        ta.execute("DELETE FROM sentences", ())?;
 
-        if table_exists && keep_blacklist {
-            conn.execute("DELETE FROM lemmas WHERE blacklisted = 0", ())?;
+        if keep_blacklist {
+            ta.execute("DELETE FROM lemmas WHERE blacklisted = 0", ())?;
         } else {
-            conn.execute_batch(
-                "DROP TABLE IF EXISTS lemmas;
-                CREATE TABLE lemmas (
-                    lemma TEXT PRIMARY KEY,
-                    frequency INTEGER NOT NULL,
-                    general_frequency INTEGER,
-                    blacklisted INTEGER NOT NULL CHECK (blacklisted IN (0, 1)),
-                    first_occurence INTEGER NOT NULL
-                );",
-            )?;
+            ta.execute("DELETE FROM lemmas", ())?;
+            ta.execute("DELETE FROM known_words", ())?;
         }
 
-        conn.execute_batch(
-            "DROP TABLE IF EXISTS sentences;
-            DROP INDEX IF EXISTS sentence_index;
-            CREATE TABLE sentences (
-                lemma TEXT NOT NULL,
-                sentence TEXT NOT NULL,
-                FOREIGN KEY(lemma) REFERENCES lemmas(lemma) ON DELETE CASCADE
-            );
-            CREATE INDEX sentence_index ON sentences(lemma)",
-        )?;
+        ta.commit()?;
 
         Ok(())
     })
@@ -50,16 +33,20 @@ pub async fn create_table(conn: &mut Connection, keep_blacklist: bool) -> Result
     Ok(())
 }
 
+/// Returns up to `limit` lemmas of the queue, skipping the first `start` ones.
+/// Lemmas are ordered by the enabled criteria, ranking well in any of them is enough to come
+/// first. If `word_classes` isn't empty, only lemmas of these word classes are returned.
 pub async fn get_lemmas_queue(
+    db: Database,
     start: usize,
+    limit: usize,
     by_frequency: bool,
     by_general_frequency: bool,
     by_first_occurence: bool,
     word_classes: HashSet<WordClass>,
 ) -> Result<Vec<String>> {
-    let conn = Connection::open("./db/database.db").await?;
-
-    let queue = conn
+    let queue = db
+        .conn
         .call(move |conn| {
             let mut args = Vec::new();
             let mut row_number_clauses = Vec::new();
@@ -154,7 +141,7 @@ pub async fn get_lemmas_queue(
                         WHERE blacklisted = 0 {} GROUP BY lemma
                     ) temp_table
                     {}
-                    LIMIT ?1,200",
+                    LIMIT ?1, ?2",
                     min_max_string,
                     row_number_clauses.join(",\n"),
                     join_str,
@@ -162,12 +149,12 @@ pub async fn get_lemmas_queue(
                     order_by_string,
                 )
             } else {
-                String::from("SELECT lemma FROM lemmas WHERE blacklisted = 0 LIMIT ?1,200")
+                String::from("SELECT lemma FROM lemmas WHERE blacklisted = 0 LIMIT ?1, ?2")
             };
 
-            let mut stmt = conn.prepare(&query)?;
+            let mut stmt = conn.prepare_cached(&query)?;
 
-            let rows = stmt.query_map([start], |row| row.get::<usize, String>(0))?;
+            let rows = stmt.query_map([start, limit], |row| row.get::<usize, String>(0))?;
 
             let mut queue = Vec::new();
 
@@ -184,54 +171,64 @@ pub async fn get_lemmas_queue(
     Ok(queue)
 }
 
-pub async fn blacklist_lemma(lemma: String) -> tokio_rusqlite::Result<()> {
-    let conn = Connection::open("./db/database.db").await?;
-
+/// Removes a lemma from the queue and remembers it as a known word.
+pub async fn blacklist_lemma(db: Database, lemma: String) -> Result<()> {
     let lemma = dictionary::remove_accents(lemma)?;
 
-    conn.call(move |conn| {
-        conn.execute(
-            "UPDATE lemmas
+    db.conn
+        .call(move |conn| {
+            known::insert(conn, &lemma)?;
+
+            conn.prepare_cached(
+                "UPDATE lemmas
                 SET blacklisted = 1
                 WHERE lemma = ?1",
-            [lemma],
-        )?;
+            )?
+            .execute([lemma])?;
 
-        Ok(())
-    })
-    .await
+            Ok(())
+        })
+        .await?;
+
+    Ok(())
 }
 
-pub async fn get_sentences(lemma: String) -> tokio_rusqlite::Result<Vec<String>> {
-    let conn = Connection::open("./db/database.db").await?;
+/// Up to `limit` sentences of the lemmatized texts the lemma occurs in.
+pub async fn get_sentences(db: Database, lemma: String, limit: usize) -> Result<Vec<String>> {
+    let sentences = db
+        .conn
+        .call(move |conn| {
+            let mut stmt =
+                conn.prepare_cached("SELECT sentence FROM sentences WHERE lemma = ?1 LIMIT ?2")?;
 
-    conn.call(move |conn| {
-        let mut stmt = conn.prepare("SELECT sentence FROM sentences WHERE lemma = ?1")?;
+            let rows = stmt.query_map(params![lemma, limit], |row| row.get(0))?;
 
-        let rows = stmt.query_map([lemma], |row| row.get(0))?;
+            let mut sentences = Vec::new();
 
-        let mut sentences = Vec::new();
+            for sentence in rows {
+                sentences.push(sentence?);
+            }
 
-        for sentence in rows {
-            sentences.push(sentence?);
-        }
+            Ok(sentences)
+        })
+        .await?;
 
-        Ok(sentences)
-    })
-    .await
+    Ok(sentences)
 }
 
-pub async fn check_queue() -> Result<bool> {
-    let conn = Connection::open("./db/database.db").await?;
-
-    let res = conn.call(|conn| {
-        let has_items = match conn.query_row("SELECT COUNT(*) FROM lemmas", [], |row| row.get::<usize, usize>(0)) {
-            Ok(count) if count > 0 => true,
-            _ => false,
-        };
+/// Whether the queue contains any lemmas, including blacklisted ones.
+pub async fn check_queue(db: Database) -> Result<bool> {
+    let res = db
+        .conn
+        .call(|conn| {
+            let has_items = matches!(
+                conn.query_row("SELECT COUNT(*) FROM lemmas", [], |row| row.get::<usize, usize>(0)),
+                Ok(count) if count > 0
+            );
 
-        Ok(has_items)
-    }).await?;
+            Ok(has_items)
+        })
+        .await?;
 
     Ok(res)
-}
\ No newline at end of file
+}
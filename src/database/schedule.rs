@@ -3,23 +3,20 @@ use std::path::PathBuf;
 use tokio::{fs, io::AsyncWriteExt};
 use tokio_rusqlite::{params, Connection};
 
-use crate::{fsrs::card::Card, Result};
+use crate::{
+    error::Error,
+    fsrs::{card::Card, Grade},
+    Result,
+};
 
-use super::queue;
+use super::{queue, Database};
 
-pub async fn create_table(conn: &mut Connection) -> Result<()> {
+/// Deletes all cards and their reviews.
+pub(super) async fn reset_table(conn: &mut Connection) -> Result<()> {
     conn.call(|conn| {
-        conn.execute("DROP TABLE IF EXISTS cards", ())?;
-        conn.execute(
-            "CREATE TABLE cards (
-                    id INTEGER PRIMARY KEY,
-                    native TEXT NOT NULL,
-                    russian TEXT NOT NULL,
-                    due INTEGER NOT NULL,
-                    stability REAL NOT NULL,
-                    difficulty REAL NOT NULL
-                )",
-            (),
+        conn.execute_batch(
+            "DELETE FROM reviews;
+            DELETE FROM cards;",
         )?;
 
         Ok(())
@@ -29,20 +26,31 @@ pub async fn create_table(conn: &mut Connection) -> Result<()> {
     Ok(())
 }
 
-pub async fn get_due_cards(conn: &mut Connection, time: u64) -> Result<Vec<Card>> {
-    let cards = conn
+/// Cards that are due at `time`, the most overdue first.
+pub async fn get_due_cards(db: Database, time: u64) -> Result<Vec<Card>> {
+    let cards = db
+        .conn
         .call(move |conn| {
             let ta = conn.transaction()?;
-            let mut stmt = ta.prepare("SELECT * FROM cards WHERE due > ?1")?;
+            let mut stmt = ta.prepare_cached(
+                "SELECT id, native, russian, stressed, grammar, due, last_review, stability,
+                    difficulty
+                FROM cards
+                WHERE due <= ?1
+                ORDER BY due",
+            )?;
 
             let cards_iter = stmt.query_map([time], |row| {
                 Ok(Card {
                     id: row.get(0)?,
                     native: row.get(1)?,
                     russian: row.get(2)?,
-                    due: row.get(3)?,
-                    stability: row.get(4)?,
-                    difficulty: row.get(5)?,
+                    stressed: row.get(3)?,
+                    grammar: row.get(4)?,
+                    due: row.get(5)?,
+                    last_review: row.get(6)?,
+                    stability: row.get(7)?,
+                    difficulty: row.get(8)?,
                 })
             })?;
             let mut cards = Vec::new();
@@ -61,85 +69,149 @@ pub async fn get_due_cards(conn: &mut Connection, time: u64) -> Result<Vec<Card>
     Ok(cards)
 }
 
-pub async fn insert_card(card: Card) -> Result<()> {
-    let conn = Connection::open("./db/database.db").await?;
+/// Adds a new card, which is due right away, and removes its word from the queue.
+pub async fn insert_card(db: Database, card: Card) -> Result<()> {
+    queue::blacklist_lemma(db.clone(), card.russian.clone()).await?;
 
-    queue::blacklist_lemma(card.russian.clone()).await?;
-
-    conn.call(move |conn| {
-        conn.execute(
-            "INSERT INTO cards(native, russian, due, stability, difficulty)
-                VALUES (?1, ?2, ?3, ?4, ?5)",
-            params![
+    db.conn
+        .call(move |conn| {
+            conn.prepare_cached(
+                "INSERT INTO cards(
+                native, russian, stressed, grammar, due, last_review, stability, difficulty
+            )
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            )?
+            .execute(params![
                 card.native,
                 card.russian,
+                card.stressed,
+                card.grammar,
                 card.due,
+                card.last_review,
                 card.stability,
                 card.difficulty
-            ],
-        )?;
-        Ok(())
-    })
-    .await?;
+            ])?;
+            Ok(())
+        })
+        .await?;
 
     Ok(())
 }
 
-pub async fn update_cards(conn: &mut Connection, cards: Vec<Card>) -> Result<()> {
-    conn.call(|conn| {
-        let ta = conn.transaction()?;
-        let mut stmt = ta.prepare(
-            "UPDATE cards
+/// Saves the memory state of reviewed cards.
+pub async fn update_cards(db: Database, cards: Vec<Card>) -> Result<()> {
+    db.conn
+        .call(|conn| {
+            let ta = conn.transaction()?;
+            let mut stmt = ta.prepare_cached(
+                "UPDATE cards
                 SET native = ?1,
                     russian = ?2,
-                    due = ?3,
-                    stability = ?4,
-                    difficulty = ?5
-                WHERE id = ?6",
-        )?;
+                    stressed = ?3,
+                    grammar = ?4,
+                    due = ?5,
+                    last_review = ?6,
+                    stability = ?7,
+                    difficulty = ?8
+                WHERE id = ?9",
+            )?;
 
-        for card in cards {
-            stmt.execute(params![
-                card.native,
-                card.russian,
-                card.due,
-                card.stability,
-                card.difficulty,
-                card.id
-            ])?;
-        }
+            for card in cards {
+                stmt.execute(params![
+                    card.native,
+                    card.russian,
+                    card.stressed,
+                    card.grammar,
+                    card.due,
+                    card.last_review,
+                    card.stability,
+                    card.difficulty,
+                    card.id
+                ])?;
+            }
 
-        drop(stmt);
+            drop(stmt);
 
-        ta.commit()?;
+            ta.commit()?;
 
-        Ok(())
-    })
-    .await?;
+            Ok(())
+        })
+        .await?;
+
+    Ok(())
+}
+
+/// Saves the memory state of a card that was just reviewed and logs the review with its grade.
+/// The time of the review is the card's last review.
+pub async fn save_review(db: Database, card: Card, grade: Grade) -> Result<()> {
+    let time = card.last_review.unwrap_or(card.due);
+    db.conn
+        .call(move |conn| {
+            let ta = conn.transaction()?;
+            ta.execute(
+                "UPDATE cards SET due = ?1, last_review = ?2, stability = ?3, difficulty = ?4
+                WHERE id = ?5",
+                params![
+                    card.due,
+                    card.last_review,
+                    card.stability,
+                    card.difficulty,
+                    card.id
+                ],
+            )?;
+            ta.execute(
+                "INSERT INTO reviews (card_id, time, grade, stability, difficulty)
+                VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![card.id, time, grade as u8, card.stability, card.difficulty],
+            )?;
+            ta.commit()?;
+
+            Ok(())
+        })
+        .await?;
 
     Ok(())
 }
 
-pub async fn export(path: PathBuf) -> Result<()> {
-    let mut file = fs::File::create(path)
+/// Writes all cards to a semicolon separated text file that Anki can import, with the columns
+/// Russian, Native, Stressed and Grammar named in the header. Unknown fields are empty, fields
+/// with separators, quotes or line breaks are quoted like in a CSV file.
+pub async fn export(db: Database, path: PathBuf) -> Result<()> {
+    let mut file = fs::File::create(&path)
         .await
-        .expect("error when attempting to create file");
+        .map_err(|e| Error::WriteFileFailed(path.clone(), e))?;
 
-    let conn = Connection::open("./db/database.db").await?;
-    let buffer = conn
+    let buffer = db
+        .conn
         .call(move |conn| {
             let mut buffer = String::new();
 
-            buffer += "#seperator:Semicolon\n";
+            buffer += "#separator:Semicolon\n";
             buffer += "#html:false\n";
+            buffer += "#columns:Russian;Native;Stressed;Grammar\n";
 
-            let mut stmt = conn.prepare("SELECT russian, native FROM cards")?;
+            let mut stmt = conn.prepare("SELECT russian, native, stressed, grammar FROM cards")?;
 
-            let rows = stmt.query_map((), |row| Ok((row.get(0)?, row.get(1)?)))?;
+            let rows = stmt.query_map((), |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })?;
 
             for row in rows {
-                let (russian, native): (String, String) = row?;
-                buffer += format!("{russian};{native}\n").as_str();
+                let (russian, native, stressed, grammar): (
+                    String,
+                    String,
+                    Option<String>,
+                    Option<String>,
+                ) = row?;
+                let fields = [
+                    russian,
+                    native,
+                    stressed.unwrap_or_default(),
+                    grammar.unwrap_or_default(),
+                ];
+                let fields: Vec<String> = fields.iter().map(|field| export_field(field)).collect();
+                buffer += &fields.join(";");
+                buffer.push('\n');
             }
 
             Ok(buffer)
@@ -148,7 +220,40 @@ pub async fn export(path: PathBuf) -> Result<()> {
 
     file.write_all(buffer.as_bytes())
         .await
-        .expect("error when writing into file");
+        .map_err(|e| Error::WriteFileFailed(path, e))?;
 
     Ok(())
 }
+
+/// A field of the export, in double quotes with quotes doubled if it would be read as more
+/// than one field or as a header line otherwise.
+fn export_field(field: &str) -> String {
+    if field.contains([';', '"', '\n', '\r']) || field.starts_with('#') {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_fields_stay_as_they_are() {
+        assert_eq!(export_field("ко́шка"), "ко́шка");
+        assert_eq!(
+            export_field("feminine, animate · gen. sg. ко́шки"),
+            "feminine, animate · gen. sg. ко́шки"
+        );
+        assert_eq!(export_field(""), "");
+    }
+
+    #[test]
+    fn fields_with_separators_are_quoted() {
+        assert_eq!(export_field("cat; pussy"), "\"cat; pussy\"");
+        assert_eq!(export_field("the \"cat\""), "\"the \"\"cat\"\"\"");
+        assert_eq!(export_field("cat\npussy"), "\"cat\npussy\"");
+        assert_eq!(export_field("#1"), "\"#1\"");
+    }
+}
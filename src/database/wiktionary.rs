@@ -0,0 +1,581 @@
+//! Parsing of the JSONL dumps from kaikki.org, one entry per line.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    io::BufRead,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, SyncSender},
+    thread,
+};
+
+use rayon::prelude::*;
+use serde::{Deserialize, Deserializer};
+
+use super::input::{Input, Source};
+use crate::{
+    dictionary::{self, entry::Relation, grammar::Grammar, WordClass},
+    error::Error,
+    progress::Progress,
+    Result,
+};
+
+/// Lines parsed at once, large enough to keep all threads busy.
+const BATCH_SIZE: usize = 4096;
+/// Parsed batches waiting to be inserted, so parsing can run ahead of the inserts.
+const QUEUED_BATCHES: usize = 4;
+
+/// An entry of the dump, with only the fields that end up in the dictionary.
+#[derive(Deserialize)]
+pub(super) struct Word {
+    pub word: String,
+    #[serde(rename = "pos", deserialize_with = "word_class")]
+    pub class: WordClass,
+    #[serde(rename = "etymology_text")]
+    pub etymology: Option<String>,
+    #[serde(default)]
+    head_templates: Vec<HeadTemplate>,
+    pub senses: Vec<Sense>,
+    #[serde(default)]
+    pub forms: Vec<Form>,
+    #[serde(default)]
+    pub sounds: Vec<Sound>,
+    #[serde(default)]
+    pub translations: Vec<Translation>,
+    #[serde(default)]
+    antonyms: Vec<Term>,
+    #[serde(default)]
+    derived: Vec<Term>,
+    #[serde(default)]
+    related: Vec<Term>,
+    #[serde(default)]
+    hypernyms: Vec<Term>,
+    #[serde(default)]
+    coordinate_terms: Vec<Term>,
+    /// The lemmas this word is a form of, filled in after parsing.
+    #[serde(skip)]
+    pub form_of: Vec<FormOf>,
+    /// The word with stress marks, filled in after parsing.
+    #[serde(skip)]
+    pub stressed: Option<String>,
+    /// Gender, animacy and aspect, filled in after parsing.
+    #[serde(skip)]
+    pub grammar: Grammar,
+}
+
+#[derive(Deserialize)]
+struct HeadTemplate {
+    expansion: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub(super) struct Sense {
+    /// The glosses of the parent senses followed by the gloss of this sense.
+    #[serde(default)]
+    pub glosses: Vec<String>,
+    #[serde(default)]
+    pub raw_glosses: Vec<String>,
+    pub qualifier: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub examples: Vec<Example>,
+    #[serde(default)]
+    pub synonyms: Vec<Term>,
+    #[serde(default)]
+    pub translations: Vec<Translation>,
+    #[serde(default)]
+    antonyms: Vec<Term>,
+    #[serde(default)]
+    derived: Vec<Term>,
+    #[serde(default)]
+    related: Vec<Term>,
+    #[serde(default)]
+    hypernyms: Vec<Term>,
+    #[serde(default)]
+    coordinate_terms: Vec<Term>,
+    #[serde(default)]
+    form_of: Vec<Term>,
+}
+
+#[derive(Deserialize)]
+pub(super) struct Example {
+    /// Some examples only have a reference or a translation, these aren't stored.
+    pub text: Option<String>,
+    pub english: Option<String>,
+}
+
+/// A synonym or related term. A few of them only have a note instead of a word.
+#[derive(Deserialize)]
+pub(super) struct Term {
+    #[serde(default)]
+    pub word: String,
+}
+
+/// A word that is documented as a form of a lemma, like a comparative or a participle, rather
+/// than in the lemma's inflection table.
+pub(super) struct FormOf {
+    pub form: String,
+    pub normalized: String,
+    pub class: WordClass,
+    pub lemma: String,
+    pub tags: Vec<String>,
+}
+
+#[derive(Deserialize)]
+pub(super) struct Translation {
+    #[serde(rename = "lang")]
+    pub language: String,
+    pub code: Option<String>,
+    /// Missing for languages that are only listed without a translation yet.
+    #[serde(default)]
+    pub word: String,
+    pub sense: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub(super) struct Form {
+    pub form: String,
+    source: Option<String>,
+    pub tags: Option<Vec<String>>,
+    /// The form without stress marks, filled in after parsing.
+    #[serde(skip)]
+    pub normalized: String,
+}
+
+#[derive(Deserialize)]
+pub(super) struct Sound {
+    pub ipa: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+fn word_class<'de, D: Deserializer<'de>>(deserializer: D) -> Result<WordClass, D::Error> {
+    let pos = String::deserialize(deserializer)?;
+    Ok(WordClass::from(pos.as_str()))
+}
+
+impl Word {
+    pub fn expansion(&self) -> Option<&str> {
+        self.head_templates.first()?.expansion.as_deref()
+    }
+
+    /// The word with stress marks, from its canonical form or else from the start of the
+    /// expansion of its head template, like "за́мок" in "за́мок • (zámok) m inan".
+    fn find_stressed(&self) -> Result<Option<String>> {
+        let canonical = self
+            .forms
+            .iter()
+            .find(|form| form.tags.iter().flatten().any(|tag| tag == "canonical"));
+        let headword = self.expansion().and_then(|expansion| {
+            let headword = expansion.split(" • ").next()?;
+            Some(headword.split(" (").next()?.trim())
+        });
+
+        for stressed in [canonical.map(|form| form.form.as_str()), headword]
+            .into_iter()
+            .flatten()
+        {
+            // a secondary stress is marked with a grave accent, like in по̀лупроводни́к
+            if dictionary::remove_accents(stressed.replace('\u{300}', ""))? == self.word {
+                return Ok(Some(stressed.to_owned()));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// The grammar from the head template and the tags of the canonical form, with the class
+    /// named by the inflection table.
+    fn find_grammar(&self) -> Grammar {
+        let has_tag =
+            |form: &&Form, tag: &str| form.tags.iter().flatten().any(|other| other == tag);
+        let tags = self
+            .forms
+            .iter()
+            .filter(|form| has_tag(form, "canonical"))
+            .flat_map(|form| form.tags.iter().flatten())
+            .map(String::as_str);
+        let inflection_class = self
+            .forms
+            .iter()
+            .find(|form| has_tag(form, "class"))
+            .map(|form| form.form.trim_start_matches("class ").to_owned());
+
+        Grammar::parse(self.expansion(), tags, inflection_class)
+    }
+
+    /// Whether the word has a meaning of its own, otherwise it's only stored as a form of its
+    /// lemmas.
+    pub fn is_lemma(&self) -> bool {
+        !self.senses.iter().all(Sense::is_form_of)
+    }
+
+    pub fn related(&self) -> impl Iterator<Item = (Relation, &str)> {
+        related([
+            (Relation::Antonym, &self.antonyms),
+            (Relation::Derived, &self.derived),
+            (Relation::Related, &self.related),
+            (Relation::Hypernym, &self.hypernyms),
+            (Relation::Coordinate, &self.coordinate_terms),
+        ])
+    }
+}
+
+impl Sense {
+    pub fn related(&self) -> impl Iterator<Item = (Relation, &str)> {
+        related([
+            (Relation::Antonym, &self.antonyms),
+            (Relation::Derived, &self.derived),
+            (Relation::Related, &self.related),
+            (Relation::Hypernym, &self.hypernyms),
+            (Relation::Coordinate, &self.coordinate_terms),
+            (Relation::FormOf, &self.form_of),
+        ])
+    }
+
+    /// Senses that only point to another entry, like "genitive singular of ...".
+    fn is_form_of(&self) -> bool {
+        !self.form_of.is_empty() || self.tags.iter().any(|tag| tag == "form-of")
+    }
+}
+
+/// The non-empty terms of each relation.
+fn related<const N: usize>(
+    lists: [(Relation, &Vec<Term>); N],
+) -> impl Iterator<Item = (Relation, &str)> {
+    lists.into_iter().flat_map(|(relation, terms)| {
+        terms
+            .iter()
+            .filter(|term| !term.word.is_empty())
+            .map(move |term| (relation, term.word.as_str()))
+    })
+}
+
+impl Form {
+    /// Only forms from inflection tables are stored, without the table's headers.
+    fn is_inflection(&self) -> bool {
+        let from_table = matches!(self.source.as_deref(), Some("declension" | "conjugation"));
+        let Some(tags) = &self.tags else {
+            return false;
+        };
+
+        from_table
+            && !tags
+                .iter()
+                .any(|tag| matches!(tag.as_str(), "inflection-template" | "table-tags" | "class"))
+    }
+}
+
+/// The number of a line that couldn't be parsed, with the reason.
+pub(super) type SkippedLine = (usize, Error);
+
+/// Parsed entries of consecutive lines.
+pub(super) struct Batch {
+    pub words: Vec<Word>,
+    /// Lines that couldn't be parsed, only in lenient mode.
+    pub skipped: Vec<SkippedLine>,
+}
+
+/// Summary of the entries a lenient import skipped.
+#[derive(Debug, Clone, Default)]
+pub struct ImportReport {
+    /// Number of skipped entries by the reason they were skipped.
+    pub skipped: BTreeMap<String, usize>,
+    /// File with the line number and reason of every skipped entry, if any were skipped.
+    pub path: Option<PathBuf>,
+}
+
+impl ImportReport {
+    pub fn skipped_total(&self) -> usize {
+        self.skipped.values().sum()
+    }
+
+    /// Counts the skipped lines by reason and writes them all to `path`. An old report is
+    /// removed if nothing was skipped, so it doesn't refer to a previous import.
+    pub(super) fn write(skipped: &[SkippedLine], path: &Path) -> Result<Self> {
+        if skipped.is_empty() {
+            if path.exists() {
+                std::fs::remove_file(path)?;
+            }
+            return Ok(ImportReport::default());
+        }
+
+        let mut report = ImportReport::default();
+        let mut text = String::new();
+        for (line, error) in skipped {
+            let reason = reason(error);
+            match error {
+                Error::InvalidEntry(_, e) => {
+                    let _ = writeln!(text, "line {line}, column {}: {reason}", e.column());
+                }
+                Error::InvalidUtf8(_, e) => {
+                    let _ = writeln!(text, "line {line}, byte {}: {reason}", e.valid_up_to() + 1);
+                }
+                _ => {
+                    let _ = writeln!(text, "line {line}: {reason}");
+                }
+            }
+            *report.skipped.entry(reason).or_default() += 1;
+        }
+
+        std::fs::write(path, text).map_err(|e| Error::WriteFileFailed(path.to_path_buf(), e))?;
+        report.path = Some(path.to_path_buf());
+
+        Ok(report)
+    }
+}
+
+/// The error message without its position, so errors of the same kind can be counted together.
+fn reason(error: &Error) -> String {
+    let error = match error {
+        Error::InvalidEntry(_, error) => error,
+        Error::InvalidUtf8(..) => return String::from("invalid UTF-8"),
+        error => return error.to_string(),
+    };
+    let message = error.to_string();
+    match message.rfind(" at line ") {
+        Some(position) => message[..position].to_owned(),
+        None => message,
+    }
+}
+
+/// Parses a single line as read from the file, which may not be valid UTF-8.
+fn parse_bytes(line: &[u8], line_number: usize) -> Result<Option<Word>> {
+    let line = std::str::from_utf8(line).map_err(|e| Error::InvalidUtf8(line_number, e))?;
+    parse_line(line, line_number)
+}
+
+/// Parses a single line, `None` for entries that aren't stored.
+fn parse_line(line: &str, line_number: usize) -> Result<Option<Word>> {
+    let mut word: Word =
+        serde_json::from_str(line).map_err(|e| Error::InvalidEntry(line_number, e))?;
+
+    if word.class == WordClass::Unknown {
+        return Ok(None);
+    }
+
+    word.stressed = word.find_stressed()?;
+    word.grammar = word.find_grammar();
+
+    let normalized = dictionary::remove_accents(word.word.clone())?;
+    for sense in &word.senses {
+        let tags: Vec<String> = sense
+            .tags
+            .iter()
+            .filter(|tag| *tag != "form-of")
+            .cloned()
+            .collect();
+
+        for lemma in sense.form_of.iter().filter(|lemma| !lemma.word.is_empty()) {
+            word.form_of.push(FormOf {
+                form: word.stressed.clone().unwrap_or_else(|| word.word.clone()),
+                normalized: normalized.clone(),
+                class: word.class.clone(),
+                lemma: dictionary::remove_accents(lemma.word.clone())?,
+                tags: tags.clone(),
+            });
+        }
+    }
+
+    if !word.is_lemma() && word.form_of.is_empty() {
+        return Ok(None);
+    }
+
+    word.forms.retain(Form::is_inflection);
+    for form in &mut word.forms {
+        form.normalized = dictionary::remove_accents(form.form.clone())?;
+    }
+
+    Ok(Some(word))
+}
+
+/// Reads and parses the dump on a separate thread, spreading each batch of lines over all cores.
+/// The batches arrive in the order of the file, reading stops at the first error or when the
+/// receiver is dropped. With `lenient`, lines that aren't valid UTF-8, can't be parsed or whose
+/// entry is invalid are skipped instead.
+pub(super) fn parse_file(
+    source: &Source,
+    lenient: bool,
+    progress: Progress,
+) -> Result<Receiver<Result<Batch>>> {
+    let input = Input::open(source, progress.clone())?;
+    let (sender, receiver) = mpsc::sync_channel(QUEUED_BATCHES);
+
+    thread::spawn(move || {
+        let path = input.name().clone();
+        let result = input.read(|reader| {
+            let mut lines = reader.split(b'\n');
+            let mut line_number = 0;
+
+            while !progress.is_cancelled() {
+                let batch = match lines
+                    .by_ref()
+                    .take(BATCH_SIZE)
+                    .collect::<std::io::Result<Vec<Vec<u8>>>>()
+                {
+                    Ok(batch) => batch,
+                    Err(e) => {
+                        let _ = sender.send(Err(Error::ReadFileFailed(path, e)));
+                        return;
+                    }
+                };
+                if batch.is_empty() {
+                    return;
+                }
+
+                let first_line = line_number + 1;
+                line_number += batch.len();
+
+                if !send_batch(&sender, &batch, first_line, lenient) {
+                    return;
+                }
+            }
+        });
+
+        if let Err(e) = result {
+            let _ = sender.send(Err(e));
+        }
+    });
+
+    Ok(receiver)
+}
+
+/// Parses the lines of a batch in parallel and sends the result, `false` if reading should stop.
+fn send_batch(
+    sender: &SyncSender<Result<Batch>>,
+    batch: &[Vec<u8>],
+    first_line: usize,
+    lenient: bool,
+) -> bool {
+    let results: Vec<Result<Option<Word>>> = batch
+        .par_iter()
+        .enumerate()
+        .map(|(i, line)| {
+            let line = line.strip_suffix(b"\r").unwrap_or(line);
+            parse_bytes(line, first_line + i)
+        })
+        .collect();
+
+    let mut parsed = Batch {
+        words: Vec::with_capacity(results.len()),
+        skipped: Vec::new(),
+    };
+    for (i, result) in results.into_iter().enumerate() {
+        match result {
+            Ok(Some(word)) => parsed.words.push(word),
+            Ok(None) => (),
+            Err(e) if lenient => parsed.skipped.push((first_line + i, e)),
+            Err(e) => {
+                let _ = sender.send(Err(e));
+                return false;
+            }
+        }
+    }
+
+    sender.send(Ok(parsed)).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KOSHKA: &str = r#"{"word": "кошка", "pos": "noun", "senses": [{"glosses": ["cat"]}]}"#;
+
+    /// Parses the lines as one batch starting at line 1.
+    fn parse_batch(lines: &[&[u8]], lenient: bool) -> Result<Batch> {
+        let (sender, receiver) = mpsc::sync_channel(1);
+        let batch: Vec<Vec<u8>> = lines.iter().map(|line| line.to_vec()).collect();
+        send_batch(&sender, &batch, 1, lenient);
+        receiver.recv().unwrap()
+    }
+
+    #[test]
+    fn parse_line_keeps_senses_without_glosses() {
+        let line = r#"{"word": "кошка", "pos": "noun", "senses": [{"glosses": []}, {}]}"#;
+        let word = parse_line(line, 1).unwrap().unwrap();
+        assert_eq!(word.senses.len(), 2);
+        assert!(word.senses.iter().all(|sense| sense.glosses.is_empty()));
+    }
+
+    #[test]
+    fn parse_line_accepts_examples_without_text() {
+        let line = r#"{"word": "кошка", "pos": "noun", "senses": [{"glosses": ["cat"],
+            "examples": [{"english": "The cat is sleeping."}, {"ref": "Chekhov"}]}]}"#;
+        let word = parse_line(line, 1).unwrap().unwrap();
+        let examples = &word.senses[0].examples;
+        assert_eq!(examples.len(), 2);
+        assert!(examples.iter().all(|example| example.text.is_none()));
+        assert_eq!(examples[0].english.as_deref(), Some("The cat is sleeping."));
+    }
+
+    #[test]
+    fn parse_line_skips_unknown_parts_of_speech() {
+        let line = r#"{"word": "кошка", "pos": "something else", "senses": []}"#;
+        assert!(parse_line(line, 1).unwrap().is_none());
+    }
+
+    #[test]
+    fn parse_line_reports_the_line_number() {
+        let error = parse_line(r#"{"word": 5}"#, 7).err().unwrap();
+        assert!(matches!(error, Error::InvalidEntry(7, _)));
+    }
+
+    #[test]
+    fn lenient_batches_skip_unreadable_lines() {
+        let lines: [&[u8]; 5] = [
+            KOSHKA.as_bytes(),
+            b"not json",
+            b"{\"word\": \"\xff\", \"pos\": \"noun\", \"senses\": []}",
+            b"",
+            "{\"word\": \"дом\", \"pos\": \"noun\", \"senses\": [{\"glosses\": [\"house\"]}]}\r"
+                .as_bytes(),
+        ];
+        let batch = parse_batch(&lines, true).unwrap();
+
+        let words: Vec<&str> = batch.words.iter().map(|word| word.word.as_str()).collect();
+        assert_eq!(words, ["кошка", "дом"]);
+        let skipped: Vec<usize> = batch.skipped.iter().map(|(line, _)| *line).collect();
+        assert_eq!(skipped, [2, 3, 4]);
+        assert!(matches!(batch.skipped[1].1, Error::InvalidUtf8(3, _)));
+    }
+
+    #[test]
+    fn strict_batches_stop_at_the_first_unreadable_line() {
+        let lines: [&[u8]; 3] = [KOSHKA.as_bytes(), b"\xff", b"not json"];
+        let error = parse_batch(&lines, false).err().unwrap();
+        assert!(matches!(error, Error::InvalidUtf8(2, _)));
+    }
+
+    #[test]
+    fn import_report_counts_reasons_and_lists_lines() {
+        let directory = std::env::temp_dir().join(format!("lern-report-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("import-report.txt");
+
+        let lines: [&[u8]; 4] = [b"not json", b"\xff", b"{\"word\": 5}", b"also not json"];
+        let batch = parse_batch(&lines, true).unwrap();
+        let report = ImportReport::write(&batch.skipped, &path).unwrap();
+
+        assert_eq!(report.skipped_total(), 4);
+        assert_eq!(report.skipped["expected ident"], 1);
+        assert_eq!(report.skipped["expected value"], 1);
+        assert_eq!(report.skipped["invalid UTF-8"], 1);
+        assert_eq!(report.path.as_deref(), Some(path.as_path()));
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "line 1, column 2: expected ident\n\
+            line 2, byte 1: invalid UTF-8\n\
+            line 3, column 10: invalid type: integer `5`, expected a string\n\
+            line 4, column 1: expected value\n"
+        );
+
+        // a report without skipped lines removes the previous one
+        let report = ImportReport::write(&[], &path).unwrap();
+        assert_eq!(report.skipped_total(), 0);
+        assert!(report.path.is_none());
+        assert!(!path.exists());
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
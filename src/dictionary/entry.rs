@@ -1,28 +1,90 @@
-use super::WordClass;
+use core::fmt;
 
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ValueRef};
+
+use super::{grammar::Grammar, WordClass};
+
+/// A word with one part of speech and etymology, as read from the dictionary.
 #[derive(Debug, Clone)]
 pub struct Entry {
     pub word: String,
     pub pos: WordClass,
     pub etymology: Option<String>,
     pub expansion: Option<String>,
+    /// The word with stress marks.
+    pub stressed: Option<String>,
+    /// Index of the stressed letter in `word`, see [`stress_position`](super::stress_position).
+    pub stress: Option<usize>,
+    pub grammar: Grammar,
     pub senses: Vec<Sense>,
     pub forms: Vec<Form>,
     pub pronunciations: Vec<Pronunciation>,
+    /// Terms related to the word as a whole rather than to one of its senses.
+    pub related: Vec<RelatedTerm>,
+    pub translations: Vec<Translation>,
+}
+
+impl Entry {
+    /// The word with stress marks if they're known.
+    pub fn stressed_word(&self) -> &str {
+        self.stressed.as_deref().unwrap_or(&self.word)
+    }
+}
+
+/// The stressed spellings of entries for the same word whose stress differs, like за́мок and
+/// замо́к. Empty if they're all stressed alike or their stress isn't known.
+pub fn stress_homographs(entries: &[Entry]) -> Vec<&str> {
+    let mut spellings: Vec<(&str, usize)> = Vec::new();
+    for entry in entries {
+        let (Some(stressed), Some(stress)) = (&entry.stressed, entry.stress) else {
+            continue;
+        };
+        if !spellings.iter().any(|(_, other)| *other == stress) {
+            spellings.push((stressed, stress));
+        }
+    }
+
+    if spellings.len() < 2 {
+        return Vec::new();
+    }
+    spellings
+        .into_iter()
+        .map(|(stressed, _)| stressed)
+        .collect()
+}
+
+/// The stressed spelling of the entries' word, `None` if it isn't known or depends on the
+/// meaning.
+pub fn stressed_spelling(entries: &[Entry]) -> Option<&str> {
+    if !stress_homographs(entries).is_empty() {
+        return None;
+    }
+
+    entries.iter().find_map(|entry| entry.stressed.as_deref())
 }
 
 #[derive(Debug, Clone)]
 pub struct Form {
+    /// The form with stress marks.
     pub form: String,
+    /// Index of the stressed letter in the form, see [`stress_position`](super::stress_position).
+    pub stress: Option<usize>,
     pub tags: Vec<String>,
 }
 
+/// A meaning of the word, which may be refined by more specific sub-senses.
 #[derive(Debug, Clone)]
 pub struct Sense {
+    /// The gloss of this sense alone, without the glosses of its parents.
     pub sense: String,
+    /// The gloss as written in wiktionary, with qualifiers like "(transitive)".
+    pub raw_sense: Option<String>,
+    pub qualifier: Option<String>,
     pub examples: Vec<Example>,
-    pub synonyms: Vec<String>,
+    pub synonyms: Vec<Synonym>,
     pub tags: Vec<String>,
+    pub related: Vec<RelatedTerm>,
+    pub children: Vec<Sense>,
 }
 
 #[derive(Debug, Clone)]
@@ -36,3 +98,66 @@ pub struct Example {
     pub text: String,
     pub english: Option<String>,
 }
+
+#[derive(Debug, Clone)]
+pub struct Synonym {
+    pub word: String,
+    /// Whether the synonym has an entry of its own that can be looked up.
+    pub has_entry: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct RelatedTerm {
+    pub relation: Relation,
+    pub word: String,
+    /// Whether the term has an entry of its own that can be looked up.
+    pub has_entry: bool,
+}
+
+/// How a related term is connected to the word.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Relation {
+    Antonym,
+    Derived,
+    Related,
+    Hypernym,
+    Coordinate,
+    /// The lemma of a sense like "genitive singular of ...".
+    FormOf,
+}
+
+impl fmt::Display for Relation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Relation::Antonym => write!(f, "antonym"),
+            Relation::Derived => write!(f, "derived"),
+            Relation::Related => write!(f, "related"),
+            Relation::Hypernym => write!(f, "hypernym"),
+            Relation::Coordinate => write!(f, "coordinate"),
+            Relation::FormOf => write!(f, "form_of"),
+        }
+    }
+}
+
+impl FromSql for Relation {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_str()? {
+            "antonym" => Ok(Relation::Antonym),
+            "derived" => Ok(Relation::Derived),
+            "related" => Ok(Relation::Related),
+            "hypernym" => Ok(Relation::Hypernym),
+            "coordinate" => Ok(Relation::Coordinate),
+            "form_of" => Ok(Relation::FormOf),
+            _ => Err(FromSqlError::InvalidType),
+        }
+    }
+}
+
+/// The word in another language, `sense` tells which meaning it translates.
+#[derive(Debug, Clone)]
+pub struct Translation {
+    pub language: String,
+    pub code: Option<String>,
+    pub word: String,
+    pub sense: Option<String>,
+}
@@ -0,0 +1,568 @@
+//! Grammatical properties of dictionary entries, like the gender of nouns and the aspect of
+//! verbs, and the forms needed to inflect them.
+
+use core::fmt;
+
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ValueRef};
+
+use super::{
+    entry::{Entry, Form},
+    normalize, WordClass,
+};
+
+/// Forms summarizing the declension of a noun, with the tags of each form.
+const NOUN_FORMS: [(&str, &[&[&str]]); 3] = [
+    ("gen. sg.", &[&["genitive", "singular"]]),
+    ("nom. pl.", &[&["nominative", "plural"]]),
+    ("gen. pl.", &[&["genitive", "plural"]]),
+];
+
+/// Forms summarizing the conjugation of a verb. Perfective verbs have no present tense, their
+/// future is used instead.
+const VERB_FORMS: [(&str, &[&[&str]]); 5] = [
+    (
+        "я",
+        &[
+            &["first-person", "singular", "present"],
+            &["first-person", "singular", "future"],
+        ],
+    ),
+    (
+        "ты",
+        &[
+            &["second-person", "singular", "present"],
+            &["second-person", "singular", "future"],
+        ],
+    ),
+    (
+        "они",
+        &[
+            &["third-person", "plural", "present"],
+            &["third-person", "plural", "future"],
+        ],
+    ),
+    ("past", &[&["masculine", "past", "singular"]]),
+    ("imperative", &[&["imperative", "singular"]]),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Gender {
+    Masculine,
+    Feminine,
+    Neuter,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Animacy {
+    Animate,
+    Inanimate,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aspect {
+    Imperfective,
+    Perfective,
+    /// Both at once, like жени́ть.
+    Biaspectual,
+}
+
+/// The declension of a noun, as taught in school.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Declension {
+    First,
+    Second,
+    Third,
+    /// Declined like an adjective, like столо́вая.
+    Adjectival,
+}
+
+/// The conjugation of a verb, told apart by the vowel of its endings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Conjugation {
+    First,
+    Second,
+}
+
+/// What the dump tells about the grammar of an entry, `None` where it doesn't.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Grammar {
+    pub gender: Option<Gender>,
+    pub animacy: Option<Animacy>,
+    pub aspect: Option<Aspect>,
+    /// The class of the declension or conjugation table, like "3*d" or "1a" in Zaliznyak's
+    /// classification.
+    pub inflection_class: Option<String>,
+}
+
+impl Grammar {
+    /// Reads the grammar from the labels after the romanization in the expansion of the head
+    /// template, like "f anim" in "ко́шка • (kóška) f anim (genitive ко́шки, ...)" or "impf" in
+    /// "чита́ть • (čitátʹ) impf (perfective прочита́ть)". Tags like "feminine" fill in what the
+    /// expansion doesn't tell.
+    pub fn parse<'a>(
+        expansion: Option<&str>,
+        tags: impl IntoIterator<Item = &'a str>,
+        inflection_class: Option<String>,
+    ) -> Grammar {
+        let head = expansion.map(head_labels).unwrap_or_default();
+        let tags: Vec<&str> = tags.into_iter().collect();
+
+        Grammar {
+            gender: gender(&head).or_else(|| gender(&tags)),
+            animacy: animacy(&head).or_else(|| animacy(&tags)),
+            aspect: aspect(&head).or_else(|| aspect(&tags)),
+            inflection_class,
+        }
+    }
+}
+
+/// The words between the romanization and the list of forms in the expansion of a head
+/// template.
+fn head_labels(expansion: &str) -> Vec<&str> {
+    let Some((_, rest)) = expansion.split_once(" • ") else {
+        return Vec::new();
+    };
+    let rest = match rest.strip_prefix('(') {
+        Some(romanized) => romanized.split_once(')').map_or("", |(_, rest)| rest),
+        None => rest,
+    };
+
+    rest.split_whitespace()
+        .take_while(|label| !label.starts_with('('))
+        .collect()
+}
+
+/// The one value whose labels appear, `None` if none or several of them do, like for nouns
+/// that are "m or f".
+fn only<T: Copy>(labels: &[&str], values: &[(T, [&str; 2])]) -> Option<T> {
+    let mut found = values
+        .iter()
+        .filter(|(_, names)| labels.iter().any(|label| names.contains(label)));
+    match (found.next(), found.next()) {
+        (Some((value, _)), None) => Some(*value),
+        _ => None,
+    }
+}
+
+fn gender(labels: &[&str]) -> Option<Gender> {
+    only(
+        labels,
+        &[
+            (Gender::Masculine, ["m", "masculine"]),
+            (Gender::Feminine, ["f", "feminine"]),
+            (Gender::Neuter, ["n", "neuter"]),
+        ],
+    )
+}
+
+fn animacy(labels: &[&str]) -> Option<Animacy> {
+    only(
+        labels,
+        &[
+            (Animacy::Animate, ["anim", "animate"]),
+            (Animacy::Inanimate, ["inan", "inanimate"]),
+        ],
+    )
+}
+
+fn aspect(labels: &[&str]) -> Option<Aspect> {
+    if labels.contains(&"biaspectual") {
+        return Some(Aspect::Biaspectual);
+    }
+
+    let imperfective = labels
+        .iter()
+        .any(|label| matches!(*label, "impf" | "imperfective"));
+    let perfective = labels
+        .iter()
+        .any(|label| matches!(*label, "pf" | "perfective"));
+    match (imperfective, perfective) {
+        (true, true) => Some(Aspect::Biaspectual),
+        (true, false) => Some(Aspect::Imperfective),
+        (false, true) => Some(Aspect::Perfective),
+        (false, false) => None,
+    }
+}
+
+/// The first form with all tags of one of the alternatives, trying them in order.
+fn find_form<'a>(forms: &'a [Form], alternatives: &[&[&str]]) -> Option<&'a Form> {
+    alternatives.iter().find_map(|tags| {
+        forms.iter().find(|form| {
+            tags.iter()
+                .all(|tag| form.tags.iter().any(|other| other == tag))
+        })
+    })
+}
+
+/// The declension of a noun, from its gender, its ending and the ending of its genitive. `None`
+/// for other words and for nouns without a declension table, which are mostly indeclinable.
+pub fn declension(entry: &Entry) -> Option<Declension> {
+    if entry.pos != WordClass::Noun || entry.forms.is_empty() {
+        return None;
+    }
+
+    let word = normalize(&entry.word);
+    let genitive = find_form(&entry.forms, &[&["genitive", "singular"]])
+        .map(|form| normalize(&form.form))
+        .unwrap_or_default();
+    let adjectival = genitive.ends_with("ого")
+        || genitive.ends_with("его")
+        || ((word.ends_with("ая") || word.ends_with("яя"))
+            && (genitive.ends_with("ой") || genitive.ends_with("ей")));
+    if adjectival {
+        return Some(Declension::Adjectival);
+    }
+
+    // the ending is enough for nouns that are "m or f" like сирота́
+    match (entry.grammar.gender, word.chars().last()?) {
+        // время and имя are declined on their own
+        (Some(Gender::Neuter), 'я') => None,
+        (_, 'а' | 'я') => Some(Declension::First),
+        (Some(Gender::Feminine), 'ь') => Some(Declension::Third),
+        (Some(Gender::Masculine | Gender::Neuter), _) => Some(Declension::Second),
+        (Some(Gender::Feminine) | None, _) => None,
+    }
+}
+
+/// The conjugation of a verb, from the ending of its second person singular. `None` for other
+/// words and for verbs like дать whose endings are irregular.
+pub fn conjugation(entry: &Entry) -> Option<Conjugation> {
+    if entry.pos != WordClass::Verb {
+        return None;
+    }
+
+    let (_, second_person) = VERB_FORMS[1];
+    let form = normalize(&find_form(&entry.forms, second_person)?.form);
+    let form = form.strip_suffix("ся").unwrap_or(&form);
+    if form.ends_with("ешь") {
+        Some(Conjugation::First)
+    } else if form.ends_with("ишь") {
+        Some(Conjugation::Second)
+    } else {
+        None
+    }
+}
+
+/// The forms needed to inflect the word, each with a short label, like "gen. sg." and ко́шки.
+pub fn key_forms(entry: &Entry) -> Vec<(&'static str, &str)> {
+    let forms: &[(&str, &[&[&str]])] = match entry.pos {
+        WordClass::Noun => &NOUN_FORMS,
+        WordClass::Verb => &VERB_FORMS,
+        _ => &[],
+    };
+
+    forms
+        .iter()
+        .filter_map(|(label, alternatives)| {
+            let form = find_form(&entry.forms, alternatives)?;
+            Some((*label, form.form.as_str()))
+        })
+        .collect()
+}
+
+/// The grammar of the entry on one line, like "feminine, animate, 1st declension · gen. sg.
+/// ко́шки, nom. pl. ко́шки, gen. pl. ко́шек". Empty if nothing is known.
+pub fn summary(entry: &Entry) -> String {
+    let grammar = &entry.grammar;
+    let properties: Vec<String> = [
+        grammar.gender.map(|gender| gender.to_string()),
+        grammar.animacy.map(|animacy| animacy.to_string()),
+        grammar.aspect.map(|aspect| aspect.to_string()),
+        declension(entry).map(|declension| declension.to_string()),
+        conjugation(entry).map(|conjugation| conjugation.to_string()),
+        grammar
+            .inflection_class
+            .as_ref()
+            .map(|class| format!("class {class}")),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+
+    let forms: Vec<String> = key_forms(entry)
+        .into_iter()
+        .map(|(label, form)| format!("{label} {form}"))
+        .collect();
+
+    [properties.join(", "), forms.join(", ")]
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(" · ")
+}
+
+/// The summary for a card of the entries' word: of the entry with the stressed spelling if
+/// there is one, otherwise of the first entry with anything to tell. `None` if nothing is known.
+pub fn word_summary(entries: &[Entry], stressed: Option<&str>) -> Option<String> {
+    let stressed_entry = stressed.and_then(|stressed| {
+        entries
+            .iter()
+            .find(|entry| entry.stressed.as_deref() == Some(stressed))
+    });
+
+    stressed_entry
+        .into_iter()
+        .chain(entries)
+        .map(summary)
+        .find(|summary| !summary.is_empty())
+}
+
+impl fmt::Display for Gender {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Gender::Masculine => write!(f, "masculine"),
+            Gender::Feminine => write!(f, "feminine"),
+            Gender::Neuter => write!(f, "neuter"),
+        }
+    }
+}
+
+impl FromSql for Gender {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_str()? {
+            "masculine" => Ok(Gender::Masculine),
+            "feminine" => Ok(Gender::Feminine),
+            "neuter" => Ok(Gender::Neuter),
+            _ => Err(FromSqlError::InvalidType),
+        }
+    }
+}
+
+impl fmt::Display for Animacy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Animacy::Animate => write!(f, "animate"),
+            Animacy::Inanimate => write!(f, "inanimate"),
+        }
+    }
+}
+
+impl FromSql for Animacy {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_str()? {
+            "animate" => Ok(Animacy::Animate),
+            "inanimate" => Ok(Animacy::Inanimate),
+            _ => Err(FromSqlError::InvalidType),
+        }
+    }
+}
+
+impl fmt::Display for Aspect {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Aspect::Imperfective => write!(f, "imperfective"),
+            Aspect::Perfective => write!(f, "perfective"),
+            Aspect::Biaspectual => write!(f, "biaspectual"),
+        }
+    }
+}
+
+impl FromSql for Aspect {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_str()? {
+            "imperfective" => Ok(Aspect::Imperfective),
+            "perfective" => Ok(Aspect::Perfective),
+            "biaspectual" => Ok(Aspect::Biaspectual),
+            _ => Err(FromSqlError::InvalidType),
+        }
+    }
+}
+
+impl fmt::Display for Declension {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Declension::First => write!(f, "1st declension"),
+            Declension::Second => write!(f, "2nd declension"),
+            Declension::Third => write!(f, "3rd declension"),
+            Declension::Adjectival => write!(f, "adjectival declension"),
+        }
+    }
+}
+
+impl fmt::Display for Conjugation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Conjugation::First => write!(f, "1st conjugation"),
+            Conjugation::Second => write!(f, "2nd conjugation"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(word: &str, pos: WordClass, expansion: &str, forms: &[(&str, &[&str])]) -> Entry {
+        Entry {
+            word: word.to_owned(),
+            pos,
+            etymology: None,
+            expansion: Some(expansion.to_owned()),
+            stressed: None,
+            stress: None,
+            grammar: Grammar::parse(Some(expansion), [], None),
+            senses: Vec::new(),
+            forms: forms
+                .iter()
+                .map(|(form, tags)| Form {
+                    form: (*form).to_owned(),
+                    stress: None,
+                    tags: tags.iter().map(|tag| (*tag).to_owned()).collect(),
+                })
+                .collect(),
+            pronunciations: Vec::new(),
+            related: Vec::new(),
+            translations: Vec::new(),
+        }
+    }
+
+    fn noun(word: &str, expansion: &str, genitive: &str) -> Entry {
+        entry(
+            word,
+            WordClass::Noun,
+            expansion,
+            &[(genitive, &["genitive", "singular"])],
+        )
+    }
+
+    fn verb(word: &str, expansion: &str, second_person: (&str, &[&str])) -> Entry {
+        entry(word, WordClass::Verb, expansion, &[second_person])
+    }
+
+    #[test]
+    fn head_labels_of_expansions() {
+        assert_eq!(
+            head_labels("ко́шка • (kóška) f anim (genitive ко́шки, nominative plural ко́шки)"),
+            ["f", "anim"]
+        );
+        assert_eq!(
+            head_labels("чита́ть • (čitátʹ) impf (perfective прочита́ть)"),
+            ["impf"]
+        );
+        assert_eq!(
+            head_labels("сирота́ • (sirotá) m or f anim"),
+            ["m", "or", "f", "anim"]
+        );
+        assert_eq!(head_labels("жени́ться • impf or pf"), ["impf", "or", "pf"]);
+        assert!(head_labels("ко́шка").is_empty());
+    }
+
+    #[test]
+    fn grammar_from_labels_and_tags() {
+        let grammar = Grammar::parse(Some("ко́шка • (kóška) f anim (genitive ко́шки)"), [], None);
+        assert_eq!(grammar.gender, Some(Gender::Feminine));
+        assert_eq!(grammar.animacy, Some(Animacy::Animate));
+        assert_eq!(grammar.aspect, None);
+
+        let grammar = Grammar::parse(None, ["masculine", "inanimate"], None);
+        assert_eq!(grammar.gender, Some(Gender::Masculine));
+        assert_eq!(grammar.animacy, Some(Animacy::Inanimate));
+    }
+
+    #[test]
+    fn declension_of_nouns() {
+        let koshka = noun("кошка", "ко́шка • (kóška) f anim (genitive ко́шки)", "ко́шки");
+        assert_eq!(declension(&koshka), Some(Declension::First));
+        let dom = noun("дом", "дом • (dom) m inan (genitive до́ма)", "до́ма");
+        assert_eq!(declension(&dom), Some(Declension::Second));
+        let mat = noun("мать", "мать • (matʹ) f anim (genitive ма́тери)", "ма́тери");
+        assert_eq!(declension(&mat), Some(Declension::Third));
+        let stolovaya = noun(
+            "столовая",
+            "столо́вая • (stolóvaja) f inan (genitive столо́вой)",
+            "столо́вой",
+        );
+        assert_eq!(declension(&stolovaya), Some(Declension::Adjectival));
+        let vremya = noun(
+            "время",
+            "вре́мя • (vrémja) n inan (genitive вре́мени)",
+            "вре́мени",
+        );
+        assert_eq!(declension(&vremya), None);
+    }
+
+    #[test]
+    fn declension_of_nouns_that_are_masculine_or_feminine() {
+        let sirota = noun(
+            "сирота",
+            "сирота́ • (sirotá) m or f anim (genitive сироты́)",
+            "сироты́",
+        );
+        assert_eq!(sirota.grammar.gender, None);
+        assert_eq!(sirota.grammar.animacy, Some(Animacy::Animate));
+        assert_eq!(declension(&sirota), Some(Declension::First));
+    }
+
+    #[test]
+    fn declension_needs_a_noun_with_forms() {
+        let kofe = entry(
+            "кофе",
+            WordClass::Noun,
+            "ко́фе • (kófe) m inan (indeclinable)",
+            &[],
+        );
+        assert_eq!(declension(&kofe), None);
+        let chitat = verb(
+            "читать",
+            "чита́ть • (čitátʹ) impf",
+            ("чита́ешь", &["second-person", "singular", "present"]),
+        );
+        assert_eq!(declension(&chitat), None);
+    }
+
+    #[test]
+    fn aspect_and_conjugation_of_verbs() {
+        let chitat = verb(
+            "читать",
+            "чита́ть • (čitátʹ) impf (perfective прочита́ть)",
+            ("чита́ешь", &["second-person", "singular", "present"]),
+        );
+        assert_eq!(chitat.grammar.aspect, Some(Aspect::Imperfective));
+        assert_eq!(conjugation(&chitat), Some(Conjugation::First));
+
+        let prochitat = verb(
+            "прочитать",
+            "прочита́ть • (pročitátʹ) pf (imperfective чита́ть)",
+            ("прочита́ешь", &["second-person", "singular", "future"]),
+        );
+        assert_eq!(prochitat.grammar.aspect, Some(Aspect::Perfective));
+        assert_eq!(conjugation(&prochitat), Some(Conjugation::First));
+    }
+
+    #[test]
+    fn conjugation_of_reflexive_verbs() {
+        let uchitsya = verb(
+            "учиться",
+            "учи́ться • (učítʹsja) impf (perfective научи́ться)",
+            ("у́чишься", &["second-person", "singular", "present"]),
+        );
+        assert_eq!(uchitsya.grammar.aspect, Some(Aspect::Imperfective));
+        assert_eq!(conjugation(&uchitsya), Some(Conjugation::Second));
+
+        let zhenitsya = verb(
+            "жениться",
+            "жени́ться • (ženítʹsja) impf or pf",
+            ("же́нишься", &["second-person", "singular", "present"]),
+        );
+        assert_eq!(zhenitsya.grammar.aspect, Some(Aspect::Biaspectual));
+        assert_eq!(conjugation(&zhenitsya), Some(Conjugation::Second));
+    }
+
+    #[test]
+    fn summary_of_a_noun() {
+        let koshka = entry(
+            "кошка",
+            WordClass::Noun,
+            "ко́шка • (kóška) f anim (genitive ко́шки, nominative plural ко́шки, genitive plural ко́шек)",
+            &[
+                ("ко́шки", &["genitive", "singular"]),
+                ("ко́шки", &["nominative", "plural"]),
+                ("ко́шек", &["genitive", "plural"]),
+            ],
+        );
+        assert_eq!(
+            summary(&koshka),
+            "feminine, animate, 1st declension · gen. sg. ко́шки, nom. pl. ко́шки, gen. pl. ко́шек"
+        );
+    }
+}
@@ -1,11 +1,11 @@
 use std::{collections::HashMap, path::PathBuf};
 
-use crate::database::dictionary;
-use crate::Result;
+use crate::database::{dictionary, Database};
+use crate::{error::Error, progress::Progress, Result};
 use regex::Regex;
-use tokio::{fs::File, io::AsyncReadExt};
+use tokio::fs;
 
-async fn lemmatize_sentences(text: String) -> Result<()> {
+async fn lemmatize_sentences(db: Database, text: String, progress: Progress) -> Result<()> {
     let regex = Regex::new(r"[^\s»—][^\r\n\t\v\f.?!…]*[.?!…\n\r\t\v\f]+»*")?;
     let sentences: Vec<String> = regex
         .find_iter(&text)
@@ -14,10 +14,11 @@ async fn lemmatize_sentences(text: String) -> Result<()> {
 
     let mut sentences_with_forms = Vec::new();
 
+    let non_word = Regex::new(r"[^А-яёЁ]")?;
+
     let mut current_word_index = 0;
     for sentence in sentences {
-        let regex = Regex::new(r"[^А-яёЁ]")?;
-        let forms: Vec<String> = regex
+        let forms: Vec<String> = non_word
             .replace_all(&sentence, " ")
             .split_whitespace()
             .map(|s| s.to_lowercase())
@@ -32,12 +33,23 @@ async fn lemmatize_sentences(text: String) -> Result<()> {
         sentences_with_forms.push((sentence, tuples));
     }
 
-    dictionary::lemmatize_sentences(sentences_with_forms).await
+    dictionary::lemmatize_sentences(db, sentences_with_forms, progress).await
+}
+
+/// Adds all lemmas of the text to the queue. With `add_sentences`, the sentences they occur in
+/// are stored as well.
+pub async fn lemmatize(db: Database, text: String, add_sentences: bool) -> Result<()> {
+    lemmatize_with_progress(db, text, add_sentences, Progress::new()).await
 }
 
-pub async fn lemmatize(text: String, add_sentences: bool) -> Result<()> {
+async fn lemmatize_with_progress(
+    db: Database,
+    text: String,
+    add_sentences: bool,
+    progress: Progress,
+) -> Result<()> {
     if add_sentences {
-        return lemmatize_sentences(text).await;
+        return lemmatize_sentences(db, text, progress).await;
     }
     let regex = Regex::new(r"[^А-яёЁ]")?;
     let forms: Vec<String> = regex
@@ -52,34 +64,28 @@ pub async fn lemmatize(text: String, add_sentences: bool) -> Result<()> {
         entry.0 += 1;
     }
 
-    dictionary::lemmatize(hash_map).await
+    dictionary::lemmatize(db, hash_map, progress).await
 }
 
-pub async fn lemmatize_from_file(path: PathBuf, add_sentences: bool) -> Result<()> {
-    let mut file = File::open(path).await?;
-    let mut text = String::new();
-    file.read_to_string(&mut text).await?;
+/// Like [`lemmatize`], with the text read from a file. The queue is only changed if the whole
+/// file was lemmatized, cancelling through `progress` leaves it as it was.
+pub async fn lemmatize_from_file(
+    db: Database,
+    path: PathBuf,
+    add_sentences: bool,
+    progress: Progress,
+) -> Result<()> {
+    let text = fs::read_to_string(&path)
+        .await
+        .map_err(|e| Error::ReadFileFailed(path, e))?;
 
-    lemmatize(text, add_sentences).await
+    lemmatize_with_progress(db, text, add_sentences, progress).await
 }
 
-pub fn remove_accents(mut word: String) -> Result<String> {
-    let patterns = vec![
-        (r"а́", "а"),
-        (r"е́", "е"),
-        (r"и́", "и"),
-        (r"о́", "о"),
-        (r"у́", "у"),
-        (r"э́", "э"),
-        (r"ы́", "ы"),
-        (r"ю́", "ю"),
-        (r"я́", "я"),
-    ];
-
-    for (pattern, replacement) in patterns {
-        let re = Regex::new(pattern)?;
-        word = re.replace_all(&word, replacement).to_string();
-    }
+/// Combining acute accent, which wiktionary puts after stressed vowels.
+pub(super) const STRESS_MARK: char = '\u{301}';
 
-    Ok(word)
+/// Removes stress marks, which wiktionary adds to most forms.
+pub fn remove_accents(word: String) -> Result<String> {
+    Ok(word.replace(STRESS_MARK, ""))
 }
@@ -1,13 +1,25 @@
+//! Lemmatization of Russian texts and the structure of dictionary entries.
+
 pub mod entry;
+pub mod grammar;
 
 mod lemmatize;
+mod spelling;
+mod stress;
 
 use core::fmt;
 
 pub use lemmatize::{lemmatize, lemmatize_from_file, remove_accents};
 use rusqlite::types::FromSql;
+use serde::{Deserialize, Serialize};
+pub use spelling::{
+    edit_distance, is_latin, normalize, transliterate, ConvertedInput, InputScheme,
+};
+pub use stress::stress_position;
 
-#[derive(PartialEq, Eq, Hash, Debug, Clone)]
+/// Part of speech of a dictionary entry.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum WordClass {
     Noun,
     Verb,
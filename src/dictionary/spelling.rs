@@ -0,0 +1,462 @@
+use core::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::lemmatize::STRESS_MARK;
+
+/// Latin letters and the Cyrillic letters they stand for in the phonetic scheme. `y` and the
+/// letters typed as two Latin ones are handled by [`InputScheme::convert`].
+const PHONETIC: [(char, char); 28] = [
+    ('a', 'а'),
+    ('b', 'б'),
+    ('v', 'в'),
+    ('w', 'в'),
+    ('g', 'г'),
+    ('d', 'д'),
+    ('e', 'е'),
+    ('z', 'з'),
+    ('i', 'и'),
+    ('j', 'й'),
+    ('k', 'к'),
+    ('q', 'к'),
+    ('l', 'л'),
+    ('m', 'м'),
+    ('n', 'н'),
+    ('o', 'о'),
+    ('p', 'п'),
+    ('r', 'р'),
+    ('s', 'с'),
+    ('t', 'т'),
+    ('u', 'у'),
+    ('f', 'ф'),
+    ('h', 'х'),
+    ('x', 'х'),
+    ('c', 'ц'),
+    ('\'', 'ь'),
+    ('"', 'ъ'),
+    ('#', 'ъ'),
+];
+
+/// Keys of a US keyboard and the letters they type in the ЯВЕРТЫ layout.
+const YAWERTY: [(char, char); 66] = [
+    ('q', 'я'),
+    ('w', 'в'),
+    ('e', 'е'),
+    ('r', 'р'),
+    ('t', 'т'),
+    ('y', 'ы'),
+    ('u', 'у'),
+    ('i', 'и'),
+    ('o', 'о'),
+    ('p', 'п'),
+    ('[', 'ш'),
+    (']', 'щ'),
+    ('\\', 'э'),
+    ('a', 'а'),
+    ('s', 'с'),
+    ('d', 'д'),
+    ('f', 'ф'),
+    ('g', 'г'),
+    ('h', 'ч'),
+    ('j', 'й'),
+    ('k', 'к'),
+    ('l', 'л'),
+    (';', 'ё'),
+    ('\'', 'ь'),
+    ('z', 'з'),
+    ('x', 'х'),
+    ('c', 'ц'),
+    ('v', 'ж'),
+    ('b', 'б'),
+    ('n', 'н'),
+    ('m', 'м'),
+    ('`', 'ю'),
+    ('=', 'ъ'),
+    ('Q', 'Я'),
+    ('W', 'В'),
+    ('E', 'Е'),
+    ('R', 'Р'),
+    ('T', 'Т'),
+    ('Y', 'Ы'),
+    ('U', 'У'),
+    ('I', 'И'),
+    ('O', 'О'),
+    ('P', 'П'),
+    ('{', 'Ш'),
+    ('}', 'Щ'),
+    ('|', 'Э'),
+    ('A', 'А'),
+    ('S', 'С'),
+    ('D', 'Д'),
+    ('F', 'Ф'),
+    ('G', 'Г'),
+    ('H', 'Ч'),
+    ('J', 'Й'),
+    ('K', 'К'),
+    ('L', 'Л'),
+    (':', 'Ё'),
+    ('"', 'Ь'),
+    ('Z', 'З'),
+    ('X', 'Х'),
+    ('C', 'Ц'),
+    ('V', 'Ж'),
+    ('B', 'Б'),
+    ('N', 'Н'),
+    ('M', 'М'),
+    ('~', 'Ю'),
+    ('+', 'Ъ'),
+];
+
+const VOWELS: &str = "аеёиоуыэюяьъ";
+
+/// How Latin letters typed into a Russian field are turned into Cyrillic ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InputScheme {
+    /// Russian spelled in Latin letters, like "privet" or "shchuka", the way it's usually
+    /// romanized. Letters typed as two Latin ones replace the first one, e.g. "s" is с until
+    /// an "h" makes it ш. ц is "c", "ts" stays тс like in "detstvo". ъ is typed as `#` or `"`,
+    /// "podyezd" is подыезд like "novye" is новые, подъезд is "pod#ezd".
+    #[default]
+    Phonetic,
+    /// The ЯВЕРТЫ layout, every key stands for one Cyrillic letter that sounds like the Latin
+    /// one, q is я.
+    Yawerty,
+}
+
+impl InputScheme {
+    pub const ALL: [InputScheme; 2] = [InputScheme::Phonetic, InputScheme::Yawerty];
+
+    /// Converts all Latin letters of the text, leaving anything else as it is. Letters typed as
+    /// two Latin ones are only combined if both are Latin, "sh" is ш but "сh" is сх.
+    pub fn convert(self, text: &str) -> String {
+        let mut input = ConvertedInput::default();
+        input.edit(self, text);
+        input.text
+    }
+}
+
+impl fmt::Display for InputScheme {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            InputScheme::Phonetic => write!(f, "phonetic"),
+            InputScheme::Yawerty => write!(f, "ЯВЕРТЫ"),
+        }
+    }
+}
+
+/// The text of a field that converts Latin letters as they are typed. It remembers which
+/// letters were converted from a Latin key, so a key typed after one of them can combine with
+/// it, while letters that were typed in Cyrillic or pasted stay as they are.
+#[derive(Debug, Clone, Default)]
+pub struct ConvertedInput {
+    text: String,
+    /// Whether each character of `text` was converted from a Latin key.
+    from_latin: Vec<bool>,
+}
+
+impl ConvertedInput {
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// Replaces the text without converting it, like a word taken from the queue.
+    pub fn set(&mut self, text: &str) {
+        self.text = text.to_owned();
+        self.from_latin = vec![false; text.chars().count()];
+    }
+
+    /// Takes the new text of the field after an edit. Only the part that differs from the
+    /// previous text is converted.
+    pub fn edit(&mut self, scheme: InputScheme, text: &str) {
+        let previous: Vec<char> = self.text.chars().collect();
+        let current: Vec<char> = text.chars().collect();
+
+        let prefix = previous
+            .iter()
+            .zip(&current)
+            .take_while(|(a, b)| a == b)
+            .count();
+        let suffix = previous[prefix..]
+            .iter()
+            .rev()
+            .zip(current[prefix..].iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+
+        let mut letters: Vec<(char, bool)> = previous[..prefix]
+            .iter()
+            .copied()
+            .zip(self.from_latin[..prefix].iter().copied())
+            .collect();
+        for &c in &current[prefix..current.len() - suffix] {
+            match scheme {
+                InputScheme::Phonetic => push_phonetic(&mut letters, c),
+                InputScheme::Yawerty => {
+                    let letter = YAWERTY.iter().find(|(key, _)| *key == c);
+                    letters.push(letter.map_or((c, false), |(_, letter)| (*letter, true)));
+                }
+            }
+        }
+        let tail = previous.len() - suffix;
+        letters.extend(
+            previous[tail..]
+                .iter()
+                .copied()
+                .zip(self.from_latin[tail..].iter().copied()),
+        );
+
+        self.text = letters.iter().map(|(c, _)| c).collect();
+        self.from_latin = letters
+            .into_iter()
+            .map(|(_, from_latin)| from_latin)
+            .collect();
+    }
+}
+
+/// Adds a character typed with the phonetic scheme to the converted letters, each with whether
+/// it was converted from a Latin key.
+fn push_phonetic(letters: &mut Vec<(char, bool)>, c: char) {
+    let key = c.to_ascii_lowercase();
+    let mut previous = letters.iter().rev();
+    let last = previous.next().copied();
+    let before_last = previous.next().copied();
+
+    // Letters typed as two Latin ones replace the letter the first one was converted to.
+    let combined = last
+        .filter(|(_, from_latin)| *from_latin)
+        .and_then(|(last, _)| {
+            let letter = match (last.to_lowercase().next()?, key) {
+                ('ц', 'h')
+                    if before_last.is_some_and(|(c, from_latin)| {
+                        from_latin && c.to_lowercase().eq(['ш'])
+                    }) =>
+                {
+                    letters.pop();
+                    'щ'
+                }
+                ('с', 'h') => 'ш',
+                ('з', 'h') => 'ж',
+                ('к', 'h') => 'х',
+                ('ц', 'h') => 'ч',
+                ('ы' | 'й', 'u') => 'ю',
+                ('ы' | 'й', 'a') => 'я',
+                ('ы' | 'й', 'o') => 'ё',
+                ('й', 'e') => 'е',
+                ('е', '`') => 'э',
+                _ => return None,
+            };
+            Some((letters.pop()?.0, letter))
+        });
+    if let Some((replaced, letter)) = combined {
+        push_with_case(letters, letter, replaced.is_uppercase());
+        return;
+    }
+
+    // "y" is й after a vowel and ы otherwise.
+    let letter = if key == 'y' {
+        let after_vowel = last.is_none_or(|(last, _)| {
+            VOWELS.contains(last.to_lowercase().next().unwrap_or(last)) || !last.is_alphabetic()
+        });
+        Some(if after_vowel { 'й' } else { 'ы' })
+    } else {
+        PHONETIC
+            .iter()
+            .find(|(latin, _)| *latin == key)
+            .map(|(_, letter)| *letter)
+    };
+
+    match letter {
+        Some(letter) => push_with_case(letters, letter, c.is_uppercase()),
+        None => letters.push((c, false)),
+    }
+}
+
+fn push_with_case(letters: &mut Vec<(char, bool)>, letter: char, uppercase: bool) {
+    if uppercase {
+        letters.extend(letter.to_uppercase().map(|letter| (letter, true)));
+    } else {
+        letters.push((letter, true));
+    }
+}
+
+/// The form words are compared in when looking them up: lowercase, without stress marks and
+/// with ё written as е, like most texts do.
+pub fn normalize(word: &str) -> String {
+    word.to_lowercase()
+        .replace(STRESS_MARK, "")
+        .replace('ё', "е")
+}
+
+/// Russian typed in Latin letters, like "privet" or "zhurnal" with the phonetic scheme, in
+/// lowercase Cyrillic, as if it was typed with the [`InputScheme`].
+pub fn transliterate(text: &str, scheme: InputScheme) -> String {
+    scheme.convert(&text.to_lowercase())
+}
+
+/// Whether the text has no Cyrillic letters, so it's either English or Russian typed in Latin
+/// letters.
+pub fn is_latin(text: &str) -> bool {
+    !text
+        .chars()
+        .any(|c| matches!(c, 'а'..='я' | 'А'..='Я' | 'ё' | 'Ё'))
+}
+
+/// The number of letters that have to be inserted, removed or replaced, or pairs of adjacent
+/// letters that have to be swapped, to turn `a` into `b`.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    // Distances from the first `i - 2`, `i - 1` and `i` letters of `a` to each start of `b`.
+    let mut before_previous = vec![0; b.len() + 1];
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+
+    for i in 1..=a.len() {
+        current[0] = i;
+        for j in 1..=b.len() {
+            let replace = previous[j - 1] + usize::from(a[i - 1] != b[j - 1]);
+            current[j] = replace.min(previous[j] + 1).min(current[j - 1] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                current[j] = current[j].min(before_previous[j - 2] + 1);
+            }
+        }
+        std::mem::swap(&mut before_previous, &mut previous);
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Types the text into a field key by key.
+    fn type_keys(scheme: InputScheme, keys: &str) -> String {
+        let mut input = ConvertedInput::default();
+        for key in keys.chars() {
+            let text = format!("{}{key}", input.as_str());
+            input.edit(scheme, &text);
+        }
+        input.as_str().to_owned()
+    }
+
+    #[test]
+    fn edit_distance_counts_edits() {
+        assert_eq!(edit_distance("привет", "привет"), 0);
+        assert_eq!(edit_distance("привет", "превет"), 1);
+        assert_eq!(edit_distance("привет", "привт"), 1);
+        assert_eq!(edit_distance("привет", "приввет"), 1);
+        assert_eq!(edit_distance("", "дом"), 3);
+        assert_eq!(edit_distance("дом", ""), 3);
+    }
+
+    #[test]
+    fn edit_distance_counts_swapped_letters_once() {
+        assert_eq!(edit_distance("привет", "пирвет"), 1);
+        assert_eq!(edit_distance("привет", "пирвте"), 2);
+    }
+
+    #[test]
+    fn transliterate_digraphs() {
+        assert_eq!(transliterate("privet", InputScheme::Phonetic), "привет");
+        assert_eq!(transliterate("Zhurnal", InputScheme::Phonetic), "журнал");
+        assert_eq!(transliterate("shchuka", InputScheme::Phonetic), "щука");
+        assert_eq!(transliterate("chasy", InputScheme::Phonetic), "часы");
+        assert_eq!(transliterate("khleb", InputScheme::Phonetic), "хлеб");
+        assert_eq!(transliterate("ulica", InputScheme::Phonetic), "улица");
+        assert_eq!(transliterate("e`to", InputScheme::Phonetic), "это");
+    }
+
+    #[test]
+    fn transliterate_t_s_as_two_letters() {
+        assert_eq!(transliterate("detstvo", InputScheme::Phonetic), "детство");
+        assert_eq!(transliterate("otsyuda", InputScheme::Phonetic), "отсюда");
+    }
+
+    #[test]
+    fn transliterate_y() {
+        assert_eq!(transliterate("yolka", InputScheme::Phonetic), "ёлка");
+        assert_eq!(transliterate("yesli", InputScheme::Phonetic), "если");
+        assert_eq!(transliterate("lyublyu", InputScheme::Phonetic), "люблю");
+        assert_eq!(transliterate("novyy", InputScheme::Phonetic), "новый");
+        assert_eq!(transliterate("novye", InputScheme::Phonetic), "новые");
+        assert_eq!(
+            transliterate("zdravstvuyte", InputScheme::Phonetic),
+            "здравствуйте"
+        );
+    }
+
+    #[test]
+    fn transliterate_hard_and_soft_signs() {
+        // "ye" after a consonant is ые, like in "novye", so ъ has to be typed
+        assert_eq!(transliterate("podyezd", InputScheme::Phonetic), "подыезд");
+        assert_eq!(transliterate("pod#ezd", InputScheme::Phonetic), "подъезд");
+        assert_eq!(transliterate("ob\"yekt", InputScheme::Phonetic), "объект");
+        assert_eq!(transliterate("ob#yekt", InputScheme::Phonetic), "объект");
+        assert_eq!(transliterate("sem'ya", InputScheme::Phonetic), "семья");
+    }
+
+    #[test]
+    fn normalize_ignores_case_stress_and_yo() {
+        assert_eq!(normalize("Ёлка"), "елка");
+        assert_eq!(normalize("за́мок"), "замок");
+        assert_eq!(normalize("всё"), "все");
+    }
+
+    #[test]
+    fn convert_keeps_everything_else() {
+        assert_eq!(
+            InputScheme::Phonetic.convert("kniga, 2 privet!"),
+            "книга, 2 привет!"
+        );
+        assert_eq!(InputScheme::Phonetic.convert("Shchuka SHCH"), "Щука Щ");
+        assert_eq!(
+            InputScheme::Yawerty.convert("qwerty [];' Privet"),
+            "яверты шщёь Прижет"
+        );
+    }
+
+    #[test]
+    fn convert_only_combines_latin_letters() {
+        assert_eq!(InputScheme::Phonetic.convert("сh"), "сх");
+        assert_eq!(InputScheme::Phonetic.convert("sh"), "ш");
+        assert_eq!(InputScheme::Phonetic.convert("шch"), "шч");
+    }
+
+    #[test]
+    fn typing_key_by_key_converts_like_the_whole_text() {
+        for scheme in InputScheme::ALL {
+            for text in [
+                "privet",
+                "Shchuka",
+                "zdravstvuyte",
+                "ob\"yekt",
+                "e`to",
+                "detstvo",
+            ] {
+                assert_eq!(type_keys(scheme, text), scheme.convert(text));
+            }
+        }
+    }
+
+    #[test]
+    fn edits_only_convert_the_inserted_text() {
+        let mut input = ConvertedInput::default();
+        input.set("сд");
+        input.edit(InputScheme::Phonetic, "сhд");
+        assert_eq!(input.as_str(), "схд");
+
+        input.edit(InputScheme::Phonetic, "sсхд");
+        input.edit(InputScheme::Phonetic, "shсхд");
+        assert_eq!(input.as_str(), "шсхд");
+
+        input.edit(InputScheme::Phonetic, "шсх");
+        assert_eq!(input.as_str(), "шсх");
+    }
+}
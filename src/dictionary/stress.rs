@@ -0,0 +1,76 @@
+use super::lemmatize::STRESS_MARK;
+
+const VOWELS: &str = "аеёиоуыэюяАЕЁИОУЫЭЮЯ";
+
+/// Index of the stressed letter of a word written with stress marks, counted in the letters of
+/// the word without them. Wiktionary marks the stress with an acute accent after the vowel, and
+/// a secondary stress with a grave accent, which is skipped; ё is always stressed and so is the
+/// only vowel of a word with one syllable. `None` if the stress isn't known.
+pub fn stress_position(word: &str) -> Option<usize> {
+    let mut letters = Vec::new();
+    for c in word.chars() {
+        if c == STRESS_MARK {
+            return letters.len().checked_sub(1);
+        }
+        if !is_combining(c) {
+            letters.push(c);
+        }
+    }
+
+    if let Some(yo) = letters.iter().position(|c| matches!(c, 'ё' | 'Ё')) {
+        return Some(yo);
+    }
+
+    let mut vowels = letters
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| VOWELS.contains(**c));
+    match (vowels.next(), vowels.next()) {
+        (Some((vowel, _)), None) => Some(vowel),
+        _ => None,
+    }
+}
+
+/// Accents and other marks that belong to the letter before them.
+fn is_combining(c: char) -> bool {
+    matches!(c, '\u{300}'..='\u{36f}')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stress_mark() {
+        assert_eq!(stress_position("за́мок"), Some(1));
+        assert_eq!(stress_position("замо́к"), Some(3));
+        assert_eq!(stress_position("Москва́"), Some(5));
+    }
+
+    #[test]
+    fn yo() {
+        assert_eq!(stress_position("всё"), Some(2));
+        assert_eq!(stress_position("ёлка"), Some(0));
+    }
+
+    #[test]
+    fn one_syllable() {
+        assert_eq!(stress_position("дом"), Some(1));
+        assert_eq!(stress_position("сплав"), Some(3));
+        assert_eq!(stress_position("в"), None);
+    }
+
+    #[test]
+    fn unknown_stress() {
+        assert_eq!(stress_position("замок"), None);
+        assert_eq!(stress_position(""), None);
+    }
+
+    #[test]
+    fn secondary_stress() {
+        assert_eq!(stress_position("по̀лупроводни́к"), Some(11));
+        assert_eq!(stress_position("фо̀тоаппара́т"), Some(9));
+        // only a secondary stress isn't enough
+        assert_eq!(stress_position("по̀лупроводник"), None);
+    }
+}
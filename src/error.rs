@@ -1,3 +1,8 @@
+//! The error type shared by the whole crate.
+
+use std::path::PathBuf;
+
+/// Everything that can go wrong in this crate.
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error("database request failed: {0}")]
@@ -8,14 +13,26 @@ pub enum Error {
     IOFailed(std::io::Error),
     #[error("error reading from jsonl file: {0}")]
     SerdeFailed(serde_json::Error),
-    #[error("error getting value from json value {0} at line {1}")]
-    GetValueFailed(serde_json::Value, usize),
-    #[error("error converting json value {0} at line {1}")]
-    ValueConversionFailed(serde_json::Value, usize),
     #[error("error when creating regex: {0}")]
     RegexFailed(regex::Error),
-    #[error("json array is empty at line {0}")]
-    EmptyJSONArray(usize),
+    #[error("invalid dictionary entry at line {0}: {1}")]
+    InvalidEntry(usize, serde_json::Error),
+    #[error("invalid UTF-8 in dictionary entry at line {0}: {1}")]
+    InvalidUtf8(usize, std::str::Utf8Error),
+    #[error("backup was created by a newer version (backup version {0})")]
+    UnsupportedBackupVersion(u32),
+    #[error("database was created by a newer version (schema version {0})")]
+    UnsupportedSchemaVersion(usize),
+    #[error("invalid profile name {0:?}, only letters, digits, '-' and '_' are allowed")]
+    InvalidProfileName(String),
+    #[error("error reading {}: {1}", .0.display())]
+    ReadFileFailed(PathBuf, std::io::Error),
+    #[error("error writing {}: {1}", .0.display())]
+    WriteFileFailed(PathBuf, std::io::Error),
+    #[error("invalid settings in {}: {1}", .0.display())]
+    InvalidSettings(PathBuf, serde_json::Error),
+    #[error("cancelled")]
+    Cancelled,
 }
 
 impl From<tokio_rusqlite::Error> for Error {
@@ -1,62 +1,97 @@
-use crate::fsrs::constants::*;
+use serde::{Deserialize, Serialize};
 
+use crate::fsrs::{constants::*, Parameters};
+
+/// A vocabulary card with its memory state.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Card {
     pub id: usize,
     pub native: String,
     pub russian: String,
-
-    pub due: u64,       //epoch timestamp
+    /// The Russian word with stress marks, if it's known.
+    #[serde(default)]
+    pub stressed: Option<String>,
+    /// A summary of the word's grammar, like its gender or aspect and key forms.
+    #[serde(default)]
+    pub grammar: Option<String>,
+
+    pub due: u64, //epoch timestamp
+    /// When the card was last reviewed, `None` if it never was.
+    #[serde(default)]
+    pub last_review: Option<u64>,
     pub stability: f64, //in days
     pub difficulty: f64,
 }
 
 impl Card {
+    /// A new card, due right away.
     pub fn new(native: &str, russian: &str) -> Self {
         Self {
             id: 0,
             native: native.to_owned(),
             russian: russian.to_owned(),
+            stressed: None,
+            grammar: None,
             due: 0,
+            last_review: None,
             stability: 0.0,
             difficulty: 0.0,
         }
     }
-    /// Updates the memory state
-    pub fn schedule(&mut self, grade: Grade, time_of_review: u64) {
-        let time = seconds_to_days(time_of_review - self.due);
+
+    pub fn with_stressed(mut self, stressed: Option<String>) -> Self {
+        self.stressed = stressed;
+        self
+    }
+
+    pub fn with_grammar(mut self, grammar: Option<String>) -> Self {
+        self.grammar = grammar;
+        self
+    }
+
+    /// Whether the card has never been reviewed.
+    pub fn is_new(&self) -> bool {
+        self.stability == 0.0
+    }
+
+    /// Updates the memory state from the time since the last review
+    pub fn schedule(&mut self, grade: Grade, time_of_review: u64, parameters: &Parameters) {
+        let last_review = self.last_review.unwrap_or(self.due);
+        let time = seconds_to_days(time_of_review.saturating_sub(last_review));
 
         let difficulty = new_difficulty(self.difficulty, grade);
         let retrievability = retrievability(time, self.stability);
         let stability = new_stability(self.stability, self.difficulty, retrievability, grade);
 
-        let interval = interval(self.stability, 0.9) as usize;
-
         self.stability = stability;
         self.difficulty = difficulty;
 
-        self.due += days_to_seconds(interval);
+        self.due = time_of_review + days_to_seconds(next_interval(self.stability, parameters));
+        self.last_review = Some(time_of_review);
     }
 
     /// First memory state
-    pub fn initial_schedule(&mut self, grade: Grade) {
-        let stability = initial_stability(grade);
-        let difficulty = initial_difficulty(grade);
-
-        let interval = interval(self.stability, 0.9) as usize;
-
-        self.stability = stability;
-        self.difficulty = difficulty;
+    pub fn initial_schedule(&mut self, grade: Grade, time_of_review: u64, parameters: &Parameters) {
+        self.stability = initial_stability(grade);
+        self.difficulty = initial_difficulty(grade);
 
-        self.due += days_to_seconds(interval);
+        self.due = time_of_review + days_to_seconds(next_interval(self.stability, parameters));
+        self.last_review = Some(time_of_review);
     }
 }
 
+/// Days until the next review, between one day and the maximum interval
+fn next_interval(stability: f64, parameters: &Parameters) -> usize {
+    (interval(stability, parameters.desired_retention).round() as usize)
+        .clamp(1, parameters.maximum_interval.max(1))
+}
+
 fn initial_stability(grade: Grade) -> f64 {
     WEIGHTS[grade as usize - 1]
 }
 
 fn initial_difficulty(grade: Grade) -> f64 {
-    WEIGHTS[4] - (grade as usize - 3) as f64 * WEIGHTS[5]
+    WEIGHTS[4] - (grade as i32 - 3) as f64 * WEIGHTS[5]
 }
 
 fn new_difficulty(difficulty: f64, grade: Grade) -> f64 {
@@ -108,3 +143,106 @@ fn post_lapse_stability(stability: f64, difficulty: f64, retrievability: f64) ->
         * ((stability + 1.0).powf(WEIGHTS[13]) - 1.0)
         * (WEIGHTS[14] * (1.0 - retrievability)).exp()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: u64 = 86400;
+
+    fn reviewed(grade: Grade, time: u64, parameters: &Parameters) -> Card {
+        let mut card = Card::new("cat", "кошка");
+        card.initial_schedule(grade, time, parameters);
+        card
+    }
+
+    #[test]
+    fn initial_schedule_uses_the_initial_memory_state() {
+        let parameters = Parameters::default();
+        for (grade, stability) in [
+            (Grade::Again, 0.4),
+            (Grade::Hard, 0.6),
+            (Grade::Good, 2.4),
+            (Grade::Easy, 5.8),
+        ] {
+            let card = reviewed(grade, 1000, &parameters);
+            assert_eq!(card.stability, stability);
+            assert_eq!(card.last_review, Some(1000));
+            assert!(!card.is_new());
+        }
+
+        // the difficulty falls with better grades, Again doesn't underflow
+        let again = reviewed(Grade::Again, 0, &parameters);
+        let easy = reviewed(Grade::Easy, 0, &parameters);
+        assert!((again.difficulty - (WEIGHTS[4] + 2.0 * WEIGHTS[5])).abs() < 1e-9);
+        assert!(again.difficulty > easy.difficulty);
+    }
+
+    #[test]
+    fn intervals_are_at_least_a_day_and_at_most_the_maximum() {
+        let parameters = Parameters::default();
+        let card = reviewed(Grade::Again, 0, &parameters);
+        assert_eq!(card.due, DAY);
+
+        // at 90% retention, the interval is the stability
+        let card = reviewed(Grade::Easy, 0, &parameters);
+        assert_eq!(card.due, 6 * DAY);
+
+        let short = Parameters {
+            maximum_interval: 3,
+            ..Parameters::default()
+        };
+        let card = reviewed(Grade::Easy, 0, &short);
+        assert_eq!(card.due, 3 * DAY);
+    }
+
+    #[test]
+    fn schedule_counts_the_days_since_the_last_review() {
+        let parameters = Parameters::default();
+        let mut on_time = reviewed(Grade::Good, 0, &parameters);
+        let mut late = on_time.clone();
+
+        on_time.schedule(Grade::Good, on_time.due, &parameters);
+        late.schedule(Grade::Good, late.due + 20 * DAY, &parameters);
+
+        assert!(on_time.stability > 2.4);
+        // remembering a card after a longer time makes it more stable
+        assert!(late.stability > on_time.stability);
+        assert_eq!(on_time.last_review, Some(2 * DAY));
+        assert_eq!(late.last_review, Some(22 * DAY));
+    }
+
+    #[test]
+    fn changed_parameters_dont_change_the_elapsed_time() {
+        let parameters = Parameters::default();
+        let card = reviewed(Grade::Good, 0, &parameters);
+        let time = 10 * DAY;
+
+        let mut with_default = card.clone();
+        with_default.schedule(Grade::Good, time, &parameters);
+
+        let mut with_changed = card;
+        let changed = Parameters {
+            desired_retention: 0.8,
+            maximum_interval: 2,
+        };
+        with_changed.schedule(Grade::Good, time, &changed);
+
+        assert_eq!(with_default.stability, with_changed.stability);
+        assert_eq!(with_default.difficulty, with_changed.difficulty);
+        assert_eq!(with_changed.due, time + 2 * DAY);
+    }
+
+    #[test]
+    fn forgotten_cards_lose_stability() {
+        let parameters = Parameters::default();
+        let mut card = reviewed(Grade::Easy, 0, &parameters);
+        card.schedule(Grade::Again, card.due, &parameters);
+
+        assert!(card.stability < 5.8);
+        assert_eq!(
+            card.due,
+            6 * DAY + DAY * next_interval(card.stability, &parameters) as u64
+        );
+    }
+}
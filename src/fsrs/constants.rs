@@ -6,6 +6,7 @@ pub const WEIGHTS: [f64; 17] = [
 pub const FACTOR: f64 = 19f64 / 81f64;
 pub const DECAY: f64 = -0.5;
 
+/// How well a card was remembered during a review.
 #[derive(Copy, Clone)]
 pub enum Grade {
     Again = 1,
@@ -14,12 +15,6 @@ pub enum Grade {
     Easy = 4,
 }
 
-/// Amount of seconds since UNIX-epoch at 5am of the day
-pub fn start_of_day(secs: u64) -> u64 {
-    let secs = secs - secs % 86400;
-    secs + 18000
-}
-
 /// Converts seconds to days
 pub fn seconds_to_days(secs: u64) -> usize {
     let secs = secs - secs % 86400;
@@ -1,7 +1,32 @@
+//! Spaced repetition scheduling with the FSRS algorithm.
+
 pub mod card;
 
 mod constants;
 
+use serde::{Deserialize, Serialize};
+
+pub use constants::Grade;
+
+/// Options of the scheduler.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Parameters {
+    /// Probability of remembering a card when it's due, higher values mean more reviews.
+    pub desired_retention: f64,
+    /// Upper bound for the time between two reviews, in days.
+    pub maximum_interval: usize,
+}
+
+impl Default for Parameters {
+    fn default() -> Self {
+        Self {
+            desired_retention: 0.9,
+            maximum_interval: 36500,
+        }
+    }
+}
+
 /*
   select all cards that are due today
   after review: update memory state
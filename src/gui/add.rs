@@ -11,26 +11,36 @@ use iced::{
     Alignment, Border, Element, Length, Task, Theme,
 };
 use iced_aw::{
-    menu::{DrawPath, Item, Menu},
+    menu::{DrawPath, Menu},
     menu_bar, menu_items,
     style::{menu_bar, Status},
     TabLabel,
 };
 use once_cell::sync::Lazy;
 
-use crate::{
-    database::{dictionary, queue, schedule},
-    dictionary::{entry::Entry, WordClass},
+use lern::{
+    database::{dictionary, queue, schedule, Database},
+    dictionary::{
+        entry::{stress_homographs, stressed_spelling, Entry},
+        grammar, ConvertedInput, InputScheme, WordClass,
+    },
     fsrs::card::Card,
 };
 
-use super::Tab;
+use crate::settings::{Change, Settings};
+
+use super::{entry, status::Notification, Tab};
 
 static INPUT_ID: Lazy<Id> = Lazy::new(Id::unique);
 
+/// Lemmas read from the queue at once.
+const QUEUE_BATCH: usize = 200;
+
 #[derive(Debug, Clone)]
 pub enum Message {
     RussianChanged(String),
+    StressedChanged(String),
+    GrammarChanged(String),
     NativeChanged(String),
     DictionaryTimer {
         version: usize,
@@ -41,6 +51,7 @@ pub enum Message {
     },
     EntriesRead {
         preloading: bool,
+        word: String,
         entries: Vec<Entry>,
     },
     ReadSentences {
@@ -67,19 +78,27 @@ pub enum Message {
     OrderFirstOccurence(bool),
     ClassButtonPressed,
     ClassToggled(bool, WordClass),
+    ConvertInput(bool),
     LinkClicked(markdown::Url),
     QueueInsertion,
     QueueEmpty,
-    Error(String),
+    Error(Notification),
 }
 
 pub enum Action {
     None,
     Run(Task<Message>),
+    Settings(Change),
+    Notify(Notification),
 }
 
 pub struct AddTab {
-    russian: String,
+    russian: ConvertedInput,
+    stressed: String,
+    /// Stressed spellings to choose from when the stress depends on the meaning.
+    homographs: Vec<String>,
+    /// Summary of the word's grammar for the card.
+    grammar: String,
     native: String,
     version: usize,
     entries: Vec<Entry>,
@@ -91,16 +110,24 @@ pub struct AddTab {
     order_general_frequency: bool,
     order_first_occurence: bool,
     word_classes: HashSet<WordClass>,
+    max_sentences: usize,
+    convert_input: bool,
+    input_scheme: InputScheme,
     ignored_from_queue: usize,
     next_word: Option<(String, Vec<Entry>)>,
     next_sentences: Option<Vec<String>>,
     markdown_items: Option<Vec<markdown::Item>>,
+    /// Words the links in the entries point to, a link's URL is `word:<index>`.
+    links: Vec<String>,
 }
 
 impl AddTab {
-    pub fn new() -> AddTab {
+    pub fn new(settings: &Settings) -> AddTab {
         AddTab {
-            russian: String::new(),
+            russian: ConvertedInput::default(),
+            stressed: String::new(),
+            homographs: Vec::new(),
+            grammar: String::new(),
             native: String::new(),
             version: 0,
             entries: Vec::new(),
@@ -111,90 +138,50 @@ impl AddTab {
             ignored_from_queue: 0,
             next_word: None,
             next_sentences: None,
-            order_frequency: true,
-            order_general_frequency: true,
-            order_first_occurence: true,
-            word_classes: HashSet::from([
-                WordClass::Noun,
-                WordClass::Adjective,
-                WordClass::Adverb,
-                WordClass::Conjunction,
-                WordClass::Determiner,
-                WordClass::Interjection,
-                WordClass::Particle,
-                WordClass::Preposition,
-                WordClass::Pronoun,
-                WordClass::Verb,
-            ]),
+            order_frequency: settings.order_frequency,
+            order_general_frequency: settings.order_general_frequency,
+            order_first_occurence: settings.order_first_occurence,
+            word_classes: settings.word_classes.clone(),
+            max_sentences: settings.max_sentences,
+            convert_input: settings.convert_input,
+            input_scheme: settings.input_scheme,
             markdown_items: None,
+            links: Vec::new(),
         }
     }
 
-    fn set_entry_markdown_items(&mut self) {
-        let mut entry_string = String::new();
-
-        for entry in &self.entries {
-            let etymology: Option<&String> = entry.etymology.as_ref();
-            let word = {
-                if let Some(expansion) = &entry.expansion {
-                    expansion
-                } else {
-                    &entry.word
-                }
-            };
-
-            if !entry.pronunciations.is_empty() {
-                entry_string += "__Pronunciation__\n\n";
-            }
-
-            for pronunciation in &entry.pronunciations {
-                let tag_string = {
-                    if pronunciation.tags.is_empty() {
-                        String::new()
-                    } else {
-                        format!(" (*{}*)", pronunciation.tags.join(", "))
-                    }
-                };
-
-                entry_string += &format!("* {}{}\n\n", pronunciation.ipa, tag_string);
-            }
-
-            let uppercase_pos = {
-                let pos = entry.pos.to_string();
-                let mut chars = pos.chars();
-                match chars.next() {
-                    None => String::new(),
-                    Some(c) => c.to_uppercase().collect::<String>() + chars.as_str(),
-                }
-            };
-
-            entry_string += &format!("__{}__\n\n{}\n\n", uppercase_pos, word);
-            if let Some(etymology) = etymology {
-                entry_string += &format!("{}\n\n", etymology);
-            }
-
-            for (i, sense) in entry.senses.iter().enumerate() {
-                let tag_string = {
-                    if sense.tags.is_empty() {
-                        String::new()
-                    } else {
-                        format!(" (*{}*)", sense.tags.join(", "))
-                    }
-                };
+    pub fn apply_settings(&mut self, settings: &Settings) {
+        self.order_frequency = settings.order_frequency;
+        self.order_general_frequency = settings.order_general_frequency;
+        self.order_first_occurence = settings.order_first_occurence;
+        settings.word_classes.clone_into(&mut self.word_classes);
+        self.max_sentences = settings.max_sentences;
+        self.convert_input = settings.convert_input;
+        self.input_scheme = settings.input_scheme;
+    }
 
-                entry_string += &format!("{}. {}{}\n\n", i + 1, sense.sense, tag_string);
+    /// Fills in the stressed word from the entries, unless the stress depends on the meaning,
+    /// and the summary of its grammar.
+    fn set_stressed(&mut self) {
+        self.homographs = stress_homographs(&self.entries)
+            .into_iter()
+            .map(str::to_owned)
+            .collect();
+        self.stressed = stressed_spelling(&self.entries)
+            .unwrap_or_default()
+            .to_owned();
+        self.set_grammar();
+    }
 
-                for example in &sense.examples {
-                    let translation = if let Some(example) = &example.english {
-                        format!(" - {}", example)
-                    } else {
-                        String::new()
-                    };
+    /// Fills in the grammar of the entry with the stressed spelling, or of the first entry.
+    fn set_grammar(&mut self) {
+        let stressed = (!self.stressed.is_empty()).then_some(self.stressed.as_str());
+        self.grammar = grammar::word_summary(&self.entries, stressed).unwrap_or_default();
+    }
 
-                    entry_string += &format!("\t{}{}\n\n", example.text, translation);
-                }
-            }
-        }
+    fn set_entry_markdown_items(&mut self) {
+        self.links.clear();
+        let mut entry_string = entry::markdown(&self.entries, &mut self.links);
 
         for sentence in &self.sentences {
             entry_string += format!("{}\n\n", sentence).as_str();
@@ -204,10 +191,14 @@ impl AddTab {
             Some(markdown::parse(&entry_string, Theme::TokyoNight.palette()).collect());
     }
 
-    pub fn update(&mut self, message: Message) -> Action {
+    pub fn update(&mut self, message: Message, db: &Database) -> Action {
         match message {
             Message::RussianChanged(value) => {
-                self.russian = value;
+                if self.convert_input {
+                    self.russian.edit(self.input_scheme, &value);
+                } else {
+                    self.russian.set(&value);
+                }
                 self.version += 1;
                 let version = self.version;
                 Action::Run(Task::perform(
@@ -218,15 +209,42 @@ impl AddTab {
                     |version| Message::DictionaryTimer { version },
                 ))
             }
+            Message::StressedChanged(value) => {
+                self.stressed = value;
+                // choosing another homograph also changes the grammar, like the gender of
+                // за́мок and замо́к
+                if self
+                    .entries
+                    .iter()
+                    .any(|entry| entry.stressed.as_ref() == Some(&self.stressed))
+                {
+                    self.set_grammar();
+                }
+                Action::None
+            }
+            Message::GrammarChanged(value) => {
+                self.grammar = value;
+                Action::None
+            }
             Message::NativeChanged(value) => {
                 self.native = value;
                 Action::None
             }
             Message::Add => {
                 if !self.native.is_empty() && !self.russian.is_empty() {
+                    let stressed = (!self.stressed.is_empty()).then(|| self.stressed.clone());
+                    let grammar = (!self.grammar.is_empty()).then(|| self.grammar.clone());
+                    let card = Card::new(&self.native, self.russian.as_str())
+                        .with_stressed(stressed)
+                        .with_grammar(grammar);
                     Action::Run(Task::perform(
-                        schedule::insert_card(Card::new(&self.native, &self.russian)),
-                        |_| Message::LoadNext,
+                        schedule::insert_card(db.clone(), card),
+                        |res| match res {
+                            Ok(()) => Message::LoadNext,
+                            Err(e) => {
+                                Message::Error(Notification::error("Adding the card failed", e))
+                            }
+                        },
                     ))
                 } else {
                     Action::None
@@ -237,11 +255,11 @@ impl AddTab {
                     Action::Run(Task::batch(vec![
                         Task::done(Message::ReadEntries {
                             preloading: false,
-                            word: self.russian.clone(),
+                            word: self.russian.as_str().to_owned(),
                         }),
                         Task::done(Message::ReadSentences {
                             preloading: false,
-                            word: self.russian.clone(),
+                            word: self.russian.as_str().to_owned(),
                         }),
                     ]))
                 } else {
@@ -250,24 +268,14 @@ impl AddTab {
             }
             Message::EntriesRead {
                 preloading,
+                word,
                 entries,
             } => {
                 if preloading {
-                    let expansion = {
-                        if let Some(entry) = entries.first() {
-                            entry.expansion.clone()
-                        } else {
-                            None
-                        }
-                    };
-                    let with_accent = if let Some(expansion) = expansion {
-                        expansion.split_whitespace().next().unwrap_or("").to_owned()
-                    } else {
-                        String::new()
-                    };
-                    self.next_word = Some((with_accent, entries));
+                    self.next_word = Some((word, entries));
                 } else {
                     self.entries = entries;
+                    self.set_stressed();
                     self.set_entry_markdown_items()
                 }
 
@@ -279,17 +287,24 @@ impl AddTab {
             }
             Message::ReadFromQueue => {
                 if self.from_queue {
-                    Action::Run(Task::future(queue::get_lemmas_queue(
-                        self.ignored_from_queue,
-                        self.order_frequency,
-                        self.order_general_frequency,
-                        self.order_first_occurence,
-                        self.word_classes.clone(),
-                    ))
-                    .then(move |lemmas| match lemmas {
-                        Ok(lemmas) => Task::done(Message::QueueRead { lemmas }),
-                        Err(e) => Task::done(Message::Error(e.to_string())),
-                    }))
+                    Action::Run(
+                        Task::future(queue::get_lemmas_queue(
+                            db.clone(),
+                            self.ignored_from_queue,
+                            QUEUE_BATCH,
+                            self.order_frequency,
+                            self.order_general_frequency,
+                            self.order_first_occurence,
+                            self.word_classes.clone(),
+                        ))
+                        .then(move |lemmas| match lemmas {
+                            Ok(lemmas) => Task::done(Message::QueueRead { lemmas }),
+                            Err(e) => Task::done(Message::Error(Notification::error(
+                                "Reading the queue failed",
+                                e,
+                            ))),
+                        }),
+                    )
                 } else {
                     Action::None
                 }
@@ -305,37 +320,39 @@ impl AddTab {
                     Action::None
                 }
             }
-            Message::Blacklist => {
-                Action::Run(Task::perform(queue::blacklist_lemma(self.russian.clone()), |_| {
-                    Message::LoadNext
-                }))
-            }
+            Message::Blacklist => Action::Run(Task::perform(
+                queue::blacklist_lemma(db.clone(), self.russian.as_str().to_owned()),
+                |res| match res {
+                    Ok(()) => Message::LoadNext,
+                    Err(e) => Message::Error(Notification::error("Blacklisting failed", e)),
+                },
+            )),
             Message::Ignore => {
                 self.ignored_from_queue += 1;
                 Action::Run(Task::done(Message::LoadNext))
             }
-            Message::Error(message) => {
-                println!("{message}");
-                Action::None
-            }
+            Message::Error(notification) => Action::Notify(notification),
             Message::ReadEntries { preloading, word } => Action::Run(Task::perform(
-                dictionary::read_entries(word),
+                dictionary::find_entries(db.clone(), word.clone(), self.input_scheme),
                 move |entries| match entries {
                     Ok(entries) => Message::EntriesRead {
                         preloading,
+                        word: word.clone(),
                         entries,
                     },
-                    Err(e) => Message::Error(e.to_string()),
+                    Err(e) => Message::Error(Notification::error("Looking up the word failed", e)),
                 },
             )),
             Message::ReadSentences { preloading, word } => Action::Run(Task::perform(
-                queue::get_sentences(word),
+                queue::get_sentences(db.clone(), word, self.max_sentences),
                 move |sentences| match sentences {
                     Ok(sentences) => Message::SentencesRead {
                         preloading,
                         sentences,
                     },
-                    Err(e) => Message::Error(e.to_string()),
+                    Err(e) => {
+                        Message::Error(Notification::error("Reading the sentences failed", e))
+                    }
                 },
             )),
             Message::SentencesRead {
@@ -358,15 +375,19 @@ impl AddTab {
             Message::LoadNext => {
                 self.version = 0;
                 self.native = String::new();
-                self.russian = String::new();
+                self.russian = ConvertedInput::default();
+                self.stressed = String::new();
+                self.homographs = Vec::new();
+                self.grammar = String::new();
 
                 if !self.from_queue {
                     return Action::None;
                 }
 
                 if let Some((lemma, entries)) = &self.next_word {
-                    lemma.clone_into(&mut self.russian);
+                    self.russian.set(lemma);
                     entries.clone_into(&mut self.entries);
+                    self.set_stressed();
                     self.set_entry_markdown_items();
                     self.next_word = None;
                 }
@@ -377,7 +398,10 @@ impl AddTab {
                     self.next_sentences = None;
                 }
 
-                Action::Run(Task::batch([Task::done(Message::Preload), focus(INPUT_ID.clone())]))
+                Action::Run(Task::batch([
+                    Task::done(Message::Preload),
+                    focus(INPUT_ID.clone()),
+                ]))
             }
             Message::Preload => {
                 if self.lemmas.is_empty() {
@@ -397,28 +421,22 @@ impl AddTab {
                 }
             }
             Message::OrderButtonPressed => Action::None,
-            Message::OrderFrequency(value) => {
-                self.order_frequency = value;
-                Action::None
-            }
+            Message::OrderFrequency(value) => Action::Settings(Change::OrderFrequency(value)),
             Message::OrderGeneralFrequency(value) => {
-                self.order_general_frequency = value;
-                Action::None
+                Action::Settings(Change::OrderGeneralFrequency(value))
             }
             Message::OrderFirstOccurence(value) => {
-                self.order_first_occurence = value;
-                Action::None
+                Action::Settings(Change::OrderFirstOccurence(value))
             }
             Message::ClassButtonPressed => Action::None,
             Message::ClassToggled(value, class) => {
-                if value {
-                    self.word_classes.insert(class);
-                } else {
-                    self.word_classes.remove(&class);
-                }
-                Action::None
+                Action::Settings(Change::WordClass(value, class))
             }
-            Message::LinkClicked(_link) => Action::None,
+            Message::ConvertInput(value) => Action::Settings(Change::ConvertInput(value)),
+            Message::LinkClicked(url) => match entry::link_target(&url, &self.links) {
+                Some(word) => Action::Run(Task::done(Message::RussianChanged(word.clone()))),
+                None => Action::None,
+            },
             Message::QueueInsertion => {
                 self.queue_available = true;
                 Action::None
@@ -570,7 +588,14 @@ impl Tab for AddTab {
             .spacing(16)
             .push(Checkbox::new("Add from queue", self.from_queue).on_toggle_maybe(from_queue_msg))
             .push(order_menu)
-            .push(word_class_menu);
+            .push(word_class_menu)
+            .push(
+                Checkbox::new(
+                    format!("Latin to Cyrillic ({})", self.input_scheme),
+                    self.convert_input,
+                )
+                .on_toggle(Message::ConvertInput),
+            );
 
         let button_row = if self.from_queue {
             Row::new()
@@ -603,11 +628,37 @@ impl Tab for AddTab {
             .padding(20)
             .spacing(16)
             .push(
-                TextInput::new("Russian", &self.russian)
+                TextInput::new("Russian", self.russian.as_str())
                     .on_input(Message::RussianChanged)
                     .padding(10)
                     .size(32),
             )
+            .push(
+                TextInput::new("Stressed", &self.stressed)
+                    .on_input(Message::StressedChanged)
+                    .padding(10)
+                    .size(24),
+            )
+            .push_maybe((!self.homographs.is_empty()).then(|| {
+                self.homographs.iter().fold(
+                    Row::new()
+                        .align_y(Alignment::Center)
+                        .spacing(8)
+                        .push(Text::new("Stress depends on the meaning:")),
+                    |row, stressed| {
+                        row.push(
+                            Button::new(Text::new(stressed))
+                                .on_press(Message::StressedChanged(stressed.clone())),
+                        )
+                    },
+                )
+            }))
+            .push(
+                TextInput::new("Grammar", &self.grammar)
+                    .on_input(Message::GrammarChanged)
+                    .padding(10)
+                    .size(16),
+            )
             .push(
                 TextInput::new("Native", &self.native)
                     .id(INPUT_ID.clone())
@@ -0,0 +1,318 @@
+use iced::{
+    alignment::{Horizontal, Vertical},
+    widget::{markdown, Button, Checkbox, Column, Container, Row, Scrollable, Text, TextInput},
+    Alignment, Element, Length, Task, Theme,
+};
+use iced_aw::TabLabel;
+
+use lern::{
+    database::{
+        dictionary::{self, SearchResult},
+        Database,
+    },
+    dictionary::{entry::Entry, ConvertedInput, InputScheme},
+};
+
+use crate::settings::{Change, Settings};
+
+use super::{entry, status::Notification, Tab};
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    QueryChanged(String),
+    SearchTimer {
+        version: usize,
+    },
+    Searched {
+        version: usize,
+        results: Vec<SearchResult>,
+    },
+    Open(String),
+    EntriesRead {
+        word: String,
+        entries: Vec<Entry>,
+    },
+    Back,
+    Forward,
+    LinkClicked(markdown::Url),
+    ConvertInput(bool),
+    Error(Notification),
+}
+
+pub enum Action {
+    None,
+    Run(Task<Message>),
+    Settings(Change),
+    Notify(Notification),
+}
+
+/// Looks words up without adding cards, with a history of the words that were opened.
+/// Converting the query to Cyrillic as it's typed is a setting of its own, since queries can be
+/// English. Without it, a query in Latin letters is searched both as English and transliterated.
+pub struct DictionaryTab {
+    query: ConvertedInput,
+    version: usize,
+    results: Vec<SearchResult>,
+    word: Option<String>,
+    markdown_items: Vec<markdown::Item>,
+    /// Words the links in the entries point to, see [`entry::markdown`].
+    links: Vec<String>,
+    history: Vec<String>,
+    /// Index of the shown word in `history`.
+    position: usize,
+    convert_input: bool,
+    input_scheme: InputScheme,
+}
+
+impl DictionaryTab {
+    pub fn new(settings: &Settings) -> DictionaryTab {
+        DictionaryTab {
+            query: ConvertedInput::default(),
+            version: 0,
+            results: Vec::new(),
+            word: None,
+            markdown_items: Vec::new(),
+            links: Vec::new(),
+            history: Vec::new(),
+            position: 0,
+            convert_input: settings.convert_dictionary_input,
+            input_scheme: settings.input_scheme,
+        }
+    }
+
+    pub fn apply_settings(&mut self, settings: &Settings) {
+        self.convert_input = settings.convert_dictionary_input;
+        self.input_scheme = settings.input_scheme;
+    }
+
+    pub fn update(&mut self, message: Message, db: &Database) -> Action {
+        match message {
+            Message::QueryChanged(query) => {
+                if self.convert_input {
+                    self.query.edit(self.input_scheme, &query);
+                } else {
+                    self.query.set(&query);
+                }
+                self.version += 1;
+                let version = self.version;
+                Action::Run(Task::perform(
+                    async move {
+                        tokio::time::sleep(tokio::time::Duration::from_millis(75)).await;
+                        version
+                    },
+                    |version| Message::SearchTimer { version },
+                ))
+            }
+            Message::SearchTimer { version } => {
+                if version != self.version {
+                    return Action::None;
+                }
+                if self.query.as_str().trim().is_empty() {
+                    self.results.clear();
+                    return Action::None;
+                }
+
+                let (db, query) = (db.clone(), self.query.as_str().to_owned());
+
+                Action::Run(Task::perform(
+                    search(db, query, self.input_scheme),
+                    move |res| match res {
+                        Ok(results) => Message::Searched { version, results },
+                        Err(e) => Message::Error(Notification::error("Searching failed", e)),
+                    },
+                ))
+            }
+            Message::Searched { version, results } => {
+                if version != self.version {
+                    return Action::None;
+                }
+
+                self.results = results;
+                match self.results.as_slice() {
+                    [result] => Action::Run(Task::done(Message::Open(result.word.clone()))),
+                    _ => Action::None,
+                }
+            }
+            Message::Open(word) => {
+                if self.history.get(self.position) != Some(&word) {
+                    if !self.history.is_empty() {
+                        self.history.truncate(self.position + 1);
+                        self.position += 1;
+                    }
+                    self.history.push(word.clone());
+                }
+
+                self.read_entries(word, db)
+            }
+            Message::Back => {
+                if self.position == 0 {
+                    return Action::None;
+                }
+
+                self.position -= 1;
+                self.read_entries(self.history[self.position].clone(), db)
+            }
+            Message::Forward => {
+                if self.position + 1 >= self.history.len() {
+                    return Action::None;
+                }
+
+                self.position += 1;
+                self.read_entries(self.history[self.position].clone(), db)
+            }
+            Message::EntriesRead { word, entries } => {
+                self.links.clear();
+                let markdown = entry::markdown(&entries, &mut self.links);
+                self.markdown_items =
+                    markdown::parse(&markdown, Theme::TokyoNight.palette()).collect();
+                self.word = Some(word);
+
+                Action::None
+            }
+            Message::LinkClicked(url) => match entry::link_target(&url, &self.links) {
+                Some(word) => Action::Run(Task::done(Message::Open(word.clone()))),
+                None => Action::None,
+            },
+            Message::ConvertInput(value) => Action::Settings(Change::ConvertDictionaryInput(value)),
+            Message::Error(notification) => Action::Notify(notification),
+        }
+    }
+
+    fn read_entries(&self, word: String, db: &Database) -> Action {
+        Action::Run(Task::perform(
+            dictionary::read_entries(db.clone(), word.clone()),
+            move |res| match res {
+                Ok(entries) => Message::EntriesRead {
+                    word: word.clone(),
+                    entries,
+                },
+                Err(e) => Message::Error(Notification::error("Looking up the word failed", e)),
+            },
+        ))
+    }
+
+    fn result_button(&self, result: &SearchResult) -> Element<'_, Message> {
+        let classes = result
+            .classes
+            .iter()
+            .map(|class| class.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let label = match (&result.gloss, &result.correction, result.is_lemma) {
+            (Some(gloss), _, _) => format!("{} ({}): {}", result.word, classes, gloss),
+            (None, _, true) => format!("{} ({})", result.word, classes),
+            (None, Some(form), false) => format!("{} ({}), form {}", result.word, classes, form),
+            (None, None, false) => format!("{} ({}), form of the query", result.word, classes),
+        };
+
+        Button::new(Text::new(label))
+            .width(Length::Fill)
+            .on_press(Message::Open(result.word.clone()))
+            .into()
+    }
+}
+
+/// Queries without Cyrillic letters are either English, which is looked for in the meanings, or
+/// Russian typed without switching the keyboard layout. Words only close to the transliterated
+/// query are shown when neither finds anything, as they're mostly noise for English queries.
+async fn search(
+    db: Database,
+    query: String,
+    scheme: InputScheme,
+) -> lern::Result<Vec<SearchResult>> {
+    if !lern::dictionary::is_latin(&query) {
+        return dictionary::search(db, query, scheme).await;
+    }
+
+    let words = dictionary::search(db.clone(), query.clone(), scheme).await?;
+    let glosses = dictionary::search_glosses(db, query).await?;
+    let (exact, close): (Vec<_>, Vec<_>) = words
+        .into_iter()
+        .partition(|result| result.correction.is_none());
+
+    if exact.is_empty() && glosses.is_empty() {
+        Ok(close)
+    } else {
+        Ok(exact.into_iter().chain(glosses).collect())
+    }
+}
+
+impl Tab for DictionaryTab {
+    type Message = super::Message;
+
+    fn title(&self) -> String {
+        String::from("Dictionary")
+    }
+
+    fn tab_label(&self) -> TabLabel {
+        TabLabel::Text(self.title())
+    }
+
+    fn content(&self) -> Element<'_, Self::Message> {
+        let navigation = Row::new()
+            .align_y(Alignment::Center)
+            .spacing(8)
+            .push(
+                Button::new(Text::new("Back"))
+                    .on_press_maybe((self.position > 0).then_some(Message::Back)),
+            )
+            .push(Button::new(Text::new("Forward")).on_press_maybe(
+                (self.position + 1 < self.history.len()).then_some(Message::Forward),
+            ))
+            .push(
+                Checkbox::new(
+                    format!("Latin to Cyrillic ({})", self.input_scheme),
+                    self.convert_input,
+                )
+                .on_toggle(Message::ConvertInput),
+            );
+
+        let results = self
+            .results
+            .iter()
+            .fold(Column::new().spacing(4), |column, result| {
+                column.push(self.result_button(result))
+            });
+        let no_results = (!self.query.as_str().trim().is_empty() && self.results.is_empty())
+            .then(|| Text::new("No entries found"));
+
+        let search_column = Column::new()
+            .align_x(Alignment::Center)
+            .max_width(400)
+            .padding(20)
+            .spacing(16)
+            .push(
+                TextInput::new("Russian word or English meaning", self.query.as_str())
+                    .on_input(Message::QueryChanged)
+                    .padding(10)
+                    .size(24),
+            )
+            .push(navigation)
+            .push(Scrollable::new(results))
+            .push_maybe(no_results);
+
+        let entries = self.word.as_ref().map(|word| {
+            Column::new()
+                .spacing(16)
+                .push(Text::new(word).size(32))
+                .push(
+                    markdown(&self.markdown_items, markdown::Settings::default())
+                        .map(Message::LinkClicked),
+                )
+        });
+
+        let content: Element<'_, Message> = Container::new(
+            Row::new()
+                .spacing(16)
+                .push(search_column.width(Length::FillPortion(1)))
+                .push_maybe(
+                    entries.map(|entries| Scrollable::new(entries).width(Length::FillPortion(2))),
+                ),
+        )
+        .align_x(Horizontal::Center)
+        .align_y(Vertical::Top)
+        .into();
+
+        content.map(super::Message::Dictionary)
+    }
+}
@@ -0,0 +1,203 @@
+use std::collections::BTreeMap;
+
+use iced::widget::markdown::Url;
+use lern::dictionary::{
+    entry::{stress_homographs, Entry, RelatedTerm, Relation, Sense, Translation},
+    grammar,
+};
+
+/// The entries as markdown. Words with an entry of their own become links to the words pushed
+/// to `links`, a link's URL is `word:<index>`.
+pub fn markdown(entries: &[Entry], links: &mut Vec<String>) -> String {
+    let mut entry_string = String::new();
+
+    let homographs = stress_homographs(entries);
+    if !homographs.is_empty() {
+        entry_string += &format!(
+            "*Stress depends on the meaning:* {}\n\n",
+            homographs.join(", ")
+        );
+    }
+
+    for entry in entries {
+        let etymology: Option<&String> = entry.etymology.as_ref();
+        let word = {
+            if let Some(expansion) = &entry.expansion {
+                expansion
+            } else {
+                &entry.word
+            }
+        };
+
+        if !entry.pronunciations.is_empty() {
+            entry_string += "__Pronunciation__\n\n";
+        }
+
+        for pronunciation in &entry.pronunciations {
+            let tag_string = {
+                if pronunciation.tags.is_empty() {
+                    String::new()
+                } else {
+                    format!(" (*{}*)", pronunciation.tags.join(", "))
+                }
+            };
+
+            entry_string += &format!("* {}{}\n\n", pronunciation.ipa, tag_string);
+        }
+
+        let uppercase_pos = {
+            let pos = entry.pos.to_string();
+            let mut chars = pos.chars();
+            match chars.next() {
+                None => String::new(),
+                Some(c) => c.to_uppercase().collect::<String>() + chars.as_str(),
+            }
+        };
+
+        entry_string += &format!("__{}__\n\n{}\n\n", uppercase_pos, word);
+        let grammar = grammar::summary(entry);
+        if !grammar.is_empty() {
+            entry_string += &format!("*{}*\n\n", grammar.replace('*', "\\*"));
+        }
+        if let Some(etymology) = etymology {
+            entry_string += &format!("{}\n\n", etymology);
+        }
+
+        for (i, sense) in entry.senses.iter().enumerate() {
+            entry_string += &sense_markdown(sense, i + 1, 0, links);
+        }
+
+        for (relation, terms) in group_related(&entry.related, links) {
+            entry_string += &format!("__{}__\n\n{}\n\n", relation_title(relation), terms);
+        }
+
+        if !entry.translations.is_empty() {
+            entry_string += "__Translations__\n\n";
+            entry_string += &translations_markdown(&entry.translations);
+        }
+    }
+
+    entry_string
+}
+
+/// The word a link created by [`markdown`] points to.
+pub fn link_target<'a>(url: &Url, links: &'a [String]) -> Option<&'a String> {
+    if url.scheme() != "word" {
+        return None;
+    }
+
+    links.get(url.path().parse::<usize>().ok()?)
+}
+
+fn link(word: &str, has_entry: bool, links: &mut Vec<String>) -> String {
+    if has_entry {
+        links.push(word.to_owned());
+        format!("[{}](word:{})", word, links.len() - 1)
+    } else {
+        word.to_owned()
+    }
+}
+
+/// A sense as a list item, with its sub-senses as a list nested `depth` levels deep.
+fn sense_markdown(sense: &Sense, number: usize, depth: usize, links: &mut Vec<String>) -> String {
+    let indent = "\t".repeat(depth);
+    let content_indent = "\t".repeat(depth + 1);
+
+    let tag_string = {
+        if sense.tags.is_empty() {
+            String::new()
+        } else {
+            format!(" (*{}*)", sense.tags.join(", "))
+        }
+    };
+
+    // The raw gloss already contains the qualifier.
+    let gloss = match (&sense.raw_sense, &sense.qualifier) {
+        (Some(raw_sense), _) => raw_sense.clone(),
+        (None, Some(qualifier)) => format!("(*{}*) {}", qualifier, sense.sense),
+        (None, None) => sense.sense.clone(),
+    };
+
+    let mut markdown = format!("{}{}. {}{}\n\n", indent, number, gloss, tag_string);
+
+    for example in &sense.examples {
+        let translation = if let Some(example) = &example.english {
+            format!(" - {}", example)
+        } else {
+            String::new()
+        };
+
+        markdown += &format!("{}{}{}\n\n", content_indent, example.text, translation);
+    }
+
+    if !sense.synonyms.is_empty() {
+        let synonyms = sense
+            .synonyms
+            .iter()
+            .map(|synonym| link(&synonym.word, synonym.has_entry, links))
+            .collect::<Vec<_>>()
+            .join(", ");
+        markdown += &format!("{}*Synonyms:* {}\n\n", content_indent, synonyms);
+    }
+    for (relation, terms) in group_related(&sense.related, links) {
+        markdown += &format!(
+            "{}*{}:* {}\n\n",
+            content_indent,
+            relation_title(relation),
+            terms
+        );
+    }
+
+    for (i, child) in sense.children.iter().enumerate() {
+        markdown += &sense_markdown(child, i + 1, depth + 1, links);
+    }
+
+    markdown
+}
+
+/// The terms of each relation, joined into one line.
+fn group_related(related: &[RelatedTerm], links: &mut Vec<String>) -> BTreeMap<Relation, String> {
+    let mut groups: BTreeMap<Relation, Vec<String>> = BTreeMap::new();
+    for term in related {
+        groups
+            .entry(term.relation)
+            .or_default()
+            .push(link(&term.word, term.has_entry, links));
+    }
+
+    groups
+        .into_iter()
+        .map(|(relation, terms)| (relation, terms.join(", ")))
+        .collect()
+}
+
+fn relation_title(relation: Relation) -> &'static str {
+    match relation {
+        Relation::Antonym => "Antonyms",
+        Relation::Derived => "Derived terms",
+        Relation::Related => "Related terms",
+        Relation::Hypernym => "Hypernyms",
+        Relation::Coordinate => "Coordinate terms",
+        Relation::FormOf => "Form of",
+    }
+}
+
+/// One line per language, with the meaning each translation belongs to.
+fn translations_markdown(translations: &[Translation]) -> String {
+    let mut languages: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for translation in translations {
+        let word = match &translation.sense {
+            Some(sense) => format!("{} ({})", translation.word, sense),
+            None => translation.word.clone(),
+        };
+        languages
+            .entry(&translation.language)
+            .or_default()
+            .push(word);
+    }
+
+    languages
+        .into_iter()
+        .map(|(language, words)| format!("* *{}:* {}\n\n", language, words.join(", ")))
+        .collect()
+}
@@ -0,0 +1,68 @@
+use iced::{
+    widget::{Button, Column, ProgressBar, Row, Text},
+    Alignment, Element,
+};
+use lern::{error::Error, progress::Progress};
+
+use super::status::Notification;
+
+/// A long-running import whose progress is shown on a tab.
+pub struct Job {
+    title: &'static str,
+    unit: &'static str,
+    progress: Progress,
+}
+
+impl Job {
+    /// `unit` names what the job counts, e.g. "entries".
+    pub fn new(title: &'static str, unit: &'static str) -> Job {
+        Job {
+            title,
+            unit,
+            progress: Progress::new(),
+        }
+    }
+
+    /// The handle to pass to the job itself.
+    pub fn progress(&self) -> Progress {
+        self.progress.clone()
+    }
+
+    pub fn cancel(&self) {
+        self.progress.cancel();
+    }
+
+    pub fn view<'a, Message: Clone + 'a>(&self, cancel: Message) -> Element<'a, Message> {
+        let cancel_button = Button::new(Text::new(if self.progress.is_cancelled() {
+            "Cancelling..."
+        } else {
+            "Cancel"
+        }))
+        .on_press_maybe((!self.progress.is_cancelled()).then_some(cancel));
+
+        Column::new()
+            .align_x(Alignment::Center)
+            .spacing(8)
+            .push(Text::new(self.title))
+            .push(
+                Row::new()
+                    .align_y(Alignment::Center)
+                    .spacing(16)
+                    .push(
+                        ProgressBar::new(0.0..=1.0, self.progress.fraction().unwrap_or(0.0))
+                            .width(300),
+                    )
+                    .push(cancel_button),
+            )
+            .push(Text::new(self.progress.describe(self.unit)).size(14))
+            .into()
+    }
+}
+
+/// The notification for a job that didn't finish, which is no error if it was cancelled.
+pub fn failed(summary: &str, error: Error) -> Notification {
+    match error {
+        Error::Cancelled => Notification::info(format!("{summary} cancelled")),
+        error => Notification::error(format!("{summary} failed"), error),
+    }
+}
@@ -8,14 +8,24 @@ use iced::{
 use iced_aw::TabLabel;
 use rfd::AsyncFileDialog;
 
-use crate::dictionary::{lemmatize, lemmatize_from_file};
+use lern::{
+    database::Database,
+    dictionary::{lemmatize, lemmatize_from_file},
+};
+
+use crate::settings::{Change, Settings};
 
-use super::Tab;
+use super::{
+    job::{self, Job},
+    status::Notification,
+    Tab,
+};
 
 pub struct LemmatizeTab {
     content: text_editor::Content,
     is_dirty: bool,
     add_sentences: bool,
+    job: Option<Job>,
 }
 
 #[derive(Debug, Clone)]
@@ -26,25 +36,40 @@ pub enum Message {
     FileSet { path: Option<PathBuf> },
     AddSentences(bool),
     Lemmatized,
-    Error(String),
+    FileLemmatized,
+    JobFailed(Notification),
+    CancelJob,
+    Error(Notification),
 }
 
 pub enum Action {
     None,
     Run(Task<Message>),
     Add(Task<super::AddMessage>),
+    Settings(Change),
+    Notify(Notification),
 }
 
 impl LemmatizeTab {
-    pub fn new() -> LemmatizeTab {
+    pub fn new(settings: &Settings) -> LemmatizeTab {
         LemmatizeTab {
             content: text_editor::Content::new(),
             is_dirty: false,
-            add_sentences: true,
+            add_sentences: settings.add_sentences,
+            job: None,
         }
     }
 
-    pub fn update(&mut self, message: Message) -> Action {
+    /// Whether a file is being lemmatized.
+    pub fn is_busy(&self) -> bool {
+        self.job.is_some()
+    }
+
+    pub fn apply_settings(&mut self, settings: &Settings) {
+        self.add_sentences = settings.add_sentences;
+    }
+
+    pub fn update(&mut self, message: Message, db: &Database) -> Action {
         match message {
             Message::ActionPerformed(action) => {
                 self.is_dirty = self.is_dirty || action.is_edit();
@@ -56,10 +81,17 @@ impl LemmatizeTab {
                 self.content = text_editor::Content::new();
                 self.is_dirty = false;
 
-                Action::Run(Task::future(lemmatize(text, self.add_sentences)).then(|result| match result {
-                    Ok(()) => Task::done(Message::Lemmatized),
-                    Err(e) => Task::done(Message::Error(e.to_string())),
-                }))
+                Action::Run(
+                    Task::future(lemmatize(db.clone(), text, self.add_sentences)).then(|result| {
+                        match result {
+                            Ok(()) => Task::done(Message::Lemmatized),
+                            Err(e) => Task::done(Message::Error(Notification::error(
+                                "Lemmatizing failed",
+                                e,
+                            ))),
+                        }
+                    }),
+                )
             }
             Message::FromFile => Action::Run(Task::perform(
                 AsyncFileDialog::new()
@@ -72,27 +104,38 @@ impl LemmatizeTab {
             )),
             Message::FileSet { path } => {
                 if let Some(path) = path {
-                    Action::Run(Task::future(lemmatize_from_file(path, self.add_sentences)).then(|result| {
-                        match result {
-                            Ok(()) => Task::none(),
-                            Err(e) => Task::done(Message::Error(e.to_string())),
-                        }
-                    }))
+                    let job = Job::new("Lemmatizing the file", "words");
+                    let progress = job.progress();
+                    self.job = Some(job);
+
+                    Action::Run(Task::perform(
+                        lemmatize_from_file(db.clone(), path, self.add_sentences, progress),
+                        |result| match result {
+                            Ok(()) => Message::FileLemmatized,
+                            Err(e) => Message::JobFailed(job::failed("Lemmatizing the file", e)),
+                        },
+                    ))
                 } else {
                     Action::None
                 }
             }
-            Message::AddSentences(value) => {
-                self.add_sentences = value;
-                Action::None
-            }
-            Message::Lemmatized => {
+            Message::AddSentences(value) => Action::Settings(Change::AddSentences(value)),
+            Message::Lemmatized => Action::Add(Task::done(super::AddMessage::QueueInsertion)),
+            Message::FileLemmatized => {
+                self.job = None;
                 Action::Add(Task::done(super::AddMessage::QueueInsertion))
             }
-            Message::Error(e) => {
-                println!("{e}");
+            Message::CancelJob => {
+                if let Some(job) = &self.job {
+                    job.cancel();
+                }
                 Action::None
             }
+            Message::JobFailed(notification) => {
+                self.job = None;
+                Action::Notify(notification)
+            }
+            Message::Error(notification) => Action::Notify(notification),
         }
     }
 }
@@ -128,7 +171,11 @@ impl Tab for LemmatizeTab {
                                 .height(Length::Fill)
                                 .on_action(Message::ActionPerformed),
                         )
-                        .push(Button::new(Text::new("From file")).on_press(Message::FromFile)),
+                        .push(
+                            Button::new(Text::new("From file"))
+                                .on_press_maybe((!self.is_busy()).then_some(Message::FromFile)),
+                        )
+                        .push_maybe(self.job.as_ref().map(|job| job.view(Message::CancelJob))),
                 )
                 .push(Button::new(Text::new("Lemmatize")).on_press(Message::Lemmatize)),
         )
@@ -2,76 +2,148 @@ use std::path::PathBuf;
 
 use iced::{
     alignment::{Horizontal, Vertical},
-    widget::{text::Shaping, Button, Checkbox, Column, Container, Row, Text},
+    widget::{text::Shaping, Button, Checkbox, Column, Container, PickList, Row, Text, TextInput},
     Alignment, Element, Task,
 };
 use iced_aw::TabLabel;
 use rfd::AsyncFileDialog;
 
-use crate::database::{self, schedule};
+use lern::database::{
+    self, backup, known,
+    profile::{self, Profile},
+    schedule, Database, ImportReport, Source,
+};
 
-use super::Tab;
+use super::{
+    job::{self, Job},
+    status::Notification,
+    Tab,
+};
 
 pub struct MainTab {
     wiktionary_path: Option<PathBuf>,
     frequency_path: Option<PathBuf>,
     pub dictionary: bool,
-    pub schedule: bool,
     pub frequency: bool,
-    pub queue: bool,
+    job: Option<Job>,
+    /// Whether entries of the dump that can't be read are skipped instead of failing the build.
+    skip_invalid_entries: bool,
     keep_blacklist: bool,
+    pending_reset: Option<Reset>,
+    profile: Profile,
+    profiles: Vec<String>,
+    new_profile: String,
+    forgotten_word: String,
+}
+
+/// Operations that delete data and have to be confirmed first.
+#[derive(Debug, Clone)]
+pub enum Reset {
+    Schedule,
+    Queue,
+    Dictionary(PathBuf),
+    Frequency(PathBuf),
+    Restore(PathBuf),
 }
 
 #[derive(Debug, Clone)]
 pub enum Message {
-    Error(String),
+    Notify(Notification),
     SetWiktionaryFile,
     SetFrequencyFile,
     WiktionaryFileSet { path: Option<PathBuf> },
     FrequencyFileSet { path: Option<PathBuf> },
-    CreateSchedule,
-    CreateQueue,
     CreateDictionary(PathBuf),
     CreateFrequency(PathBuf),
-    ScheduleCreated,
-    QueueCreated,
-    DictionaryCreated,
+    DictionaryCreated(ImportReport),
     FrequencyCreated,
+    JobFailed(Notification),
+    CancelJob,
     SetExportLocation,
     Export { path: Option<PathBuf> },
     Exported,
+    SkipInvalidEntries(bool),
     KeepBlacklist(bool),
+    SetKnownWordsFile,
+    ImportKnownWords { path: Option<PathBuf> },
+    KnownWordsImported(usize),
+    SetKnownWordsExportLocation,
+    ExportKnownWords { path: Option<PathBuf> },
+    KnownWordsExported,
+    ForgottenWordChanged(String),
+    ForgetWord,
+    WordForgotten { word: String, known: bool },
+    SetBackupLocation,
+    Backup { path: Option<PathBuf> },
+    BackedUp,
+    SetRestoreFile,
+    Restore { path: Option<PathBuf> },
+    Restored,
+    ResetRequested(Reset),
+    ResetConfirmed,
+    ResetCancelled,
+    ScheduleReset(PathBuf),
+    QueueReset,
+    ProfileSelected(String),
+    NewProfileChanged(String),
+    CreateProfile,
 }
 
 pub enum Action {
     None,
     Run(Task<Message>),
     Add(Task<super::AddMessage>),
+    OpenProfile(Profile),
+    Notify(Notification),
 }
 
 impl MainTab {
-    pub fn new() -> MainTab {
+    pub fn new(profile: Profile) -> MainTab {
+        let mut profiles = profile::list(profile.data_dir()).unwrap_or_default();
+        if !profiles.iter().any(|name| name == profile.name()) {
+            profiles.push(profile.name().to_owned());
+            profiles.sort();
+        }
+
         MainTab {
             wiktionary_path: None,
             frequency_path: None,
             dictionary: false,
-            schedule: false,
             frequency: false,
-            queue: false,
+            job: None,
+            skip_invalid_entries: true,
             keep_blacklist: true,
+            pending_reset: None,
+            profile,
+            profiles,
+            new_profile: String::new(),
+            forgotten_word: String::new(),
         }
     }
 
-    pub fn update(&mut self, message: Message) -> Action {
+    /// Whether an import is running.
+    pub fn is_busy(&self) -> bool {
+        self.job.is_some()
+    }
+
+    fn switch_profile(&self, name: String) -> Action {
+        if name == self.profile.name() {
+            return Action::None;
+        }
+
+        match Profile::new(self.profile.data_dir().to_path_buf(), name) {
+            Ok(profile) => Action::OpenProfile(profile),
+            Err(e) => Action::Notify(Notification::error("Can't open the profile", e)),
+        }
+    }
+
+    pub fn update(&mut self, message: Message, db: &Database) -> Action {
         match message {
-            Message::Error(e) => {
-                println!("{e}");
-                Action::None
-            }
+            Message::Notify(notification) => Action::Notify(notification),
             Message::SetWiktionaryFile => Action::Run(Task::perform(
                 AsyncFileDialog::new()
                     .set_title("Wiktionary")
-                    .add_filter("JSON Lines", &["jsonl"])
+                    .add_filter("JSON Lines", &["jsonl", "gz", "bz2", "xz", "zip"])
                     .pick_file(),
                 |file_handle| Message::WiktionaryFileSet {
                     path: file_handle.map(|file_handle| file_handle.into()),
@@ -98,47 +170,76 @@ impl MainTab {
                 }
                 Action::None
             }
-            Message::CreateSchedule => {
-                Action::Run(Task::perform(database::create_schedule(), |res| match res {
-                    Err(e) => Message::Error(e.to_string()),
-                    Ok(()) => Message::ScheduleCreated,
-                }))
+            Message::CreateDictionary(path) => {
+                let job = Job::new("Creating the dictionary", "entries");
+                let progress = job.progress();
+                self.job = Some(job);
+
+                Action::Run(Task::perform(
+                    database::create_dictionary(
+                        db.clone(),
+                        Source::File(path),
+                        self.skip_invalid_entries,
+                        progress,
+                    ),
+                    |res| match res {
+                        Err(e) => Message::JobFailed(job::failed("Creating the dictionary", e)),
+                        Ok(report) => Message::DictionaryCreated(report),
+                    },
+                ))
             }
-            Message::CreateQueue => Action::Run(Task::perform(
-                database::create_queue(self.keep_blacklist),
-                |res| match res {
-                    Err(e) => Message::Error(e.to_string()),
-                    Ok(()) => Message::QueueCreated,
-                },
-            )),
-            Message::CreateDictionary(path) => Action::Run(Task::perform(
-                database::create_dictionary(path),
-                |res| match res {
-                    Err(e) => Message::Error(e.to_string()),
-                    Ok(()) => Message::DictionaryCreated,
-                },
-            )),
-            Message::CreateFrequency(path) => Action::Run(Task::perform(
-                database::create_frequency(path),
-                |res| match res {
-                    Err(e) => Message::Error(e.to_string()),
-                    Ok(()) => Message::FrequencyCreated,
-                },
-            )),
-            Message::ScheduleCreated => {
-                self.schedule = true;
-                Action::None
+            Message::CreateFrequency(path) => {
+                let job = Job::new("Creating the frequency table", "words");
+                let progress = job.progress();
+                self.job = Some(job);
+
+                Action::Run(Task::perform(
+                    database::create_frequency(db.clone(), path, progress),
+                    |res| match res {
+                        Err(e) => {
+                            Message::JobFailed(job::failed("Creating the frequency table", e))
+                        }
+                        Ok(()) => Message::FrequencyCreated,
+                    },
+                ))
             }
-            Message::DictionaryCreated => {
+            Message::DictionaryCreated(report) => {
                 self.dictionary = true;
-                Action::None
-            }
-            Message::QueueCreated => {
-                self.queue = true;
-                Action::Add(Task::done(super::AddMessage::QueueEmpty))
+                self.job = None;
+
+                let Some(path) = report.path.as_ref() else {
+                    return Action::Notify(Notification::info("Dictionary created"));
+                };
+
+                let mut details = report
+                    .skipped
+                    .iter()
+                    .map(|(reason, count)| format!("{count} × {reason}"))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                details += &format!("\nAll skipped lines are listed in {}", path.display());
+
+                Action::Notify(
+                    Notification::info(format!(
+                        "Dictionary created, {} entries were skipped",
+                        report.skipped_total()
+                    ))
+                    .with_details(details),
+                )
             }
             Message::FrequencyCreated => {
                 self.frequency = true;
+                self.job = None;
+                Action::Notify(Notification::info("Frequency table created"))
+            }
+            Message::JobFailed(notification) => {
+                self.job = None;
+                Action::Notify(notification)
+            }
+            Message::CancelJob => {
+                if let Some(job) = &self.job {
+                    job.cancel();
+                }
                 Action::None
             }
             Message::SetExportLocation => Action::Run(Task::perform(
@@ -153,20 +254,218 @@ impl MainTab {
                 },
             )),
             Message::Export { path } => match path {
-                Some(path) => Action::Run(Task::perform(schedule::export(path), |res| match res {
-                    Ok(()) => Message::Exported,
-                    Err(e) => Message::Error(e.to_string()),
-                })),
+                Some(path) => Action::Run(Task::perform(
+                    schedule::export(db.clone(), path),
+                    |res| match res {
+                        Ok(()) => Message::Exported,
+                        Err(e) => {
+                            Message::Notify(Notification::error("Exporting the cards failed", e))
+                        }
+                    },
+                )),
                 None => Action::None,
             },
-            Message::Exported => {
-                println!("exported!");
+            Message::Exported => Action::Notify(Notification::info("Cards exported")),
+            Message::SkipInvalidEntries(skip_invalid_entries) => {
+                self.skip_invalid_entries = skip_invalid_entries;
                 Action::None
             }
             Message::KeepBlacklist(keep_blacklist) => {
                 self.keep_blacklist = keep_blacklist;
                 Action::None
             }
+            Message::SetKnownWordsFile => Action::Run(Task::perform(
+                AsyncFileDialog::new()
+                    .set_title("Known words")
+                    .add_filter("text", &["txt"])
+                    .pick_file(),
+                |file_handle| Message::ImportKnownWords {
+                    path: file_handle.map(|file_handle| file_handle.into()),
+                },
+            )),
+            Message::ImportKnownWords { path } => {
+                match path {
+                    Some(path) => Action::Run(Task::perform(
+                        known::import(db.clone(), path),
+                        |res| match res {
+                            Ok(count) => Message::KnownWordsImported(count),
+                            Err(e) => Message::Notify(Notification::error(
+                                "Importing known words failed",
+                                e,
+                            )),
+                        },
+                    )),
+                    None => Action::None,
+                }
+            }
+            Message::KnownWordsImported(count) => {
+                Action::Notify(Notification::info(format!("Imported {count} known words")))
+            }
+            Message::SetKnownWordsExportLocation => Action::Run(Task::perform(
+                AsyncFileDialog::new()
+                    .set_title("Export known words")
+                    .add_filter("text", &["txt"])
+                    .set_directory("Downloads")
+                    .set_file_name("KnownWords")
+                    .save_file(),
+                |file_handle| Message::ExportKnownWords {
+                    path: file_handle.map(|file_handle| file_handle.into()),
+                },
+            )),
+            Message::ExportKnownWords { path } => {
+                match path {
+                    Some(path) => Action::Run(Task::perform(
+                        known::export(db.clone(), path),
+                        |res| match res {
+                            Ok(()) => Message::KnownWordsExported,
+                            Err(e) => Message::Notify(Notification::error(
+                                "Exporting known words failed",
+                                e,
+                            )),
+                        },
+                    )),
+                    None => Action::None,
+                }
+            }
+            Message::KnownWordsExported => {
+                Action::Notify(Notification::info("Known words exported"))
+            }
+            Message::ForgottenWordChanged(word) => {
+                self.forgotten_word = word;
+                Action::None
+            }
+            Message::ForgetWord => {
+                let word = self.forgotten_word.trim().to_owned();
+                Action::Run(Task::perform(
+                    known::remove(db.clone(), word.clone()),
+                    move |res| match res {
+                        Ok(known) => Message::WordForgotten {
+                            word: word.clone(),
+                            known,
+                        },
+                        Err(e) => {
+                            Message::Notify(Notification::error("Forgetting the word failed", e))
+                        }
+                    },
+                ))
+            }
+            Message::WordForgotten { word, known } => {
+                if known {
+                    self.forgotten_word.clear();
+                    Action::Notify(Notification::info(format!("Forgot the known word {word}")))
+                } else {
+                    Action::Notify(Notification::info(format!("{word} isn't a known word")))
+                }
+            }
+            Message::SetBackupLocation => Action::Run(Task::perform(
+                AsyncFileDialog::new()
+                    .set_title("Backup")
+                    .add_filter("JSON", &["json"])
+                    .set_file_name("LernBackup.json")
+                    .save_file(),
+                |file_handle| Message::Backup {
+                    path: file_handle.map(|file_handle| file_handle.into()),
+                },
+            )),
+            Message::Backup { path } => {
+                match path {
+                    Some(path) => Action::Run(Task::perform(
+                        backup::backup(db.clone(), path),
+                        |res| match res {
+                            Ok(()) => Message::BackedUp,
+                            Err(e) => Message::Notify(Notification::error("Backup failed", e)),
+                        },
+                    )),
+                    None => Action::None,
+                }
+            }
+            Message::BackedUp => Action::Notify(Notification::info("Backup saved")),
+            Message::SetRestoreFile => Action::Run(Task::perform(
+                AsyncFileDialog::new()
+                    .set_title("Restore")
+                    .add_filter("JSON", &["json"])
+                    .pick_file(),
+                |file_handle| Message::Restore {
+                    path: file_handle.map(|file_handle| file_handle.into()),
+                },
+            )),
+            Message::Restore { path } => {
+                if let Some(path) = path {
+                    self.pending_reset = Some(Reset::Restore(path));
+                }
+                Action::None
+            }
+            // the profile is opened again, so the restored settings apply
+            Message::Restored => Action::OpenProfile(self.profile.clone()),
+            Message::ResetRequested(reset) => {
+                self.pending_reset = Some(reset);
+                Action::None
+            }
+            Message::ResetCancelled => {
+                self.pending_reset = None;
+                Action::None
+            }
+            Message::ResetConfirmed => match self.pending_reset.take() {
+                Some(Reset::Schedule) => Action::Run(Task::perform(
+                    database::reset_schedule(db.clone()),
+                    |res| match res {
+                        Err(e) => {
+                            Message::Notify(Notification::error("Resetting the schedule failed", e))
+                        }
+                        Ok(backup) => Message::ScheduleReset(backup),
+                    },
+                )),
+                Some(Reset::Queue) => Action::Run(
+                    Task::future(database::reset_queue(db.clone(), self.keep_blacklist)).then(
+                        |res| match res {
+                            Err(e) => Task::done(Message::Notify(Notification::error(
+                                "Resetting the queue failed",
+                                e,
+                            ))),
+                            Ok(backup) => Task::done(Message::Notify(Notification::info(format!(
+                                "Queue reset, backup saved to {}",
+                                backup.display()
+                            ))))
+                            .chain(Task::done(Message::QueueReset)),
+                        },
+                    ),
+                ),
+                Some(Reset::Dictionary(path)) => {
+                    Action::Run(Task::done(Message::CreateDictionary(path)))
+                }
+                Some(Reset::Frequency(path)) => {
+                    Action::Run(Task::done(Message::CreateFrequency(path)))
+                }
+                Some(Reset::Restore(path)) => {
+                    Action::Run(Task::future(backup::restore(db.clone(), path)).then(|res| {
+                        match res {
+                            Ok(()) => {
+                                Task::done(Message::Notify(Notification::info("Backup restored")))
+                                    .chain(Task::done(Message::Restored))
+                            }
+                            Err(e) => Task::done(Message::Notify(Notification::error(
+                                "Restoring the backup failed",
+                                e,
+                            ))),
+                        }
+                    }))
+                }
+                None => Action::None,
+            },
+            Message::ScheduleReset(backup) => Action::Notify(Notification::info(format!(
+                "Schedule reset, backup saved to {}",
+                backup.display()
+            ))),
+            Message::QueueReset => Action::Add(Task::done(super::AddMessage::QueueEmpty)),
+            Message::ProfileSelected(name) => self.switch_profile(name),
+            Message::NewProfileChanged(name) => {
+                self.new_profile = name;
+                Action::None
+            }
+            Message::CreateProfile => {
+                let name = self.new_profile.trim().to_owned();
+                self.switch_profile(name)
+            }
         }
     }
 }
@@ -183,12 +482,28 @@ impl Tab for MainTab {
     }
 
     fn content(&self) -> iced::Element<'_, Self::Message> {
-        let frequency_msg = if self.dictionary {
-            self.frequency_path.clone().map(Message::CreateFrequency)
+        let frequency_msg = if self.dictionary && !self.is_busy() {
+            self.frequency_path.clone().map(|path| {
+                if self.frequency {
+                    Message::ResetRequested(Reset::Frequency(path))
+                } else {
+                    Message::CreateFrequency(path)
+                }
+            })
         } else {
             None
         };
-        let dictionary_msg: Option<Message> = self.wiktionary_path.clone().map(Message::CreateDictionary);
+        let dictionary_msg: Option<Message> = self
+            .wiktionary_path
+            .clone()
+            .filter(|_| !self.is_busy())
+            .map(|path| {
+                if self.dictionary {
+                    Message::ResetRequested(Reset::Dictionary(path))
+                } else {
+                    Message::CreateDictionary(path)
+                }
+            });
 
         let dictionary = {
             if self.dictionary {
@@ -206,21 +521,25 @@ impl Tab for MainTab {
             }
         };
 
-        let schedule = {
-            if self.schedule {
-                Some(Text::new("✓").shaping(Shaping::Advanced))
-            } else {
-                None
-            }
-        };
-
-        let queue = {
-            if self.queue {
-                Some(Text::new("✓").shaping(Shaping::Advanced))
-            } else {
-                None
-            }
-        };
+        let profile_row = Row::new()
+            .align_y(Alignment::Center)
+            .padding(20)
+            .spacing(16)
+            .push(Text::new("Profile"))
+            .push(PickList::new(
+                self.profiles.as_slice(),
+                Some(self.profile.name().to_owned()),
+                Message::ProfileSelected,
+            ))
+            .push(
+                TextInput::new("New profile", &self.new_profile)
+                    .on_input(Message::NewProfileChanged)
+                    .on_submit(Message::CreateProfile)
+                    .width(160),
+            )
+            .push(Button::new(Text::new("Create profile")).on_press_maybe(
+                (!self.new_profile.trim().is_empty()).then_some(Message::CreateProfile),
+            ));
 
         let file_row = Row::new()
             .align_y(Alignment::Center)
@@ -238,7 +557,18 @@ impl Tab for MainTab {
             .padding(20)
             .spacing(16)
             .push_maybe(dictionary)
-            .push(Button::new(Text::new("Create dictionary")).on_press_maybe(dictionary_msg))
+            .push(
+                Button::new(Text::new(if self.dictionary {
+                    "Rebuild dictionary"
+                } else {
+                    "Create dictionary"
+                }))
+                .on_press_maybe(dictionary_msg),
+            )
+            .push(
+                Checkbox::new("Skip unreadable entries", self.skip_invalid_entries)
+                    .on_toggle(Message::SkipInvalidEntries),
+            )
             .push(Button::new(Text::new("Create frequencies")).on_press_maybe(frequency_msg))
             .push_maybe(frequency);
 
@@ -246,23 +576,95 @@ impl Tab for MainTab {
             .align_y(Alignment::Center)
             .padding(20)
             .spacing(16)
-            .push_maybe(schedule)
-            .push(Button::new(Text::new("Create schedule")).on_press(Message::CreateSchedule))
-            .push(Button::new(Text::new("Create queue")).on_press(Message::CreateQueue))
+            .push(
+                Button::new(Text::new("Reset schedule"))
+                    .on_press(Message::ResetRequested(Reset::Schedule)),
+            )
+            .push(
+                Button::new(Text::new("Reset queue"))
+                    .on_press(Message::ResetRequested(Reset::Queue)),
+            )
             .push(
                 Checkbox::new("Keep blacklist", self.keep_blacklist)
                     .on_toggle(Message::KeepBlacklist),
+            );
+
+        let known_words_row = Row::new()
+            .align_y(Alignment::Center)
+            .padding(20)
+            .spacing(16)
+            .push(Button::new(Text::new("Import known words")).on_press(Message::SetKnownWordsFile))
+            .push(
+                Button::new(Text::new("Export known words"))
+                    .on_press(Message::SetKnownWordsExportLocation),
+            )
+            .push(
+                TextInput::new("Known word", &self.forgotten_word)
+                    .on_input(Message::ForgottenWordChanged)
+                    .on_submit(Message::ForgetWord)
+                    .width(160),
             )
-            .push_maybe(queue);
+            .push(Button::new(Text::new("Forget")).on_press_maybe(
+                (!self.forgotten_word.trim().is_empty()).then_some(Message::ForgetWord),
+            ));
+
+        let backup_row = Row::new()
+            .align_y(Alignment::Center)
+            .padding(20)
+            .spacing(16)
+            .push(Button::new(Text::new("Backup")).on_press(Message::SetBackupLocation))
+            .push(Button::new(Text::new("Restore")).on_press(Message::SetRestoreFile));
+
+        let confirm_row = self.pending_reset.as_ref().map(|reset| {
+            let warning = match reset {
+                Reset::Schedule => String::from("This deletes all of your cards and their reviews."),
+                Reset::Queue if self.keep_blacklist => String::from(
+                    "This deletes all lemmas and sentences in the queue, except for blacklisted lemmas.",
+                ),
+                Reset::Queue => String::from(
+                    "This deletes all lemmas and sentences in the queue and forgets all known words.",
+                ),
+                Reset::Dictionary(_) => String::from("This replaces the current dictionary."),
+                Reset::Frequency(_) => String::from("This replaces the current frequencies."),
+                Reset::Restore(_) => String::from(
+                    "This replaces your cards, queue and known words with the contents of the backup.",
+                ),
+            };
+
+            Column::new()
+                .align_x(Alignment::Center)
+                .spacing(8)
+                .push(Text::new(warning))
+                .push(Text::new(format!(
+                    "A backup of the current state will be saved in {}.",
+                    match reset {
+                        Reset::Dictionary(_) | Reset::Frequency(_) =>
+                            self.profile.dictionary_backup_directory(),
+                        _ => self.profile.backup_directory(),
+                    }
+                    .display()
+                )))
+                .push(
+                    Row::new()
+                        .spacing(16)
+                        .push(Button::new(Text::new("Confirm")).on_press(Message::ResetConfirmed))
+                        .push(Button::new(Text::new("Cancel")).on_press(Message::ResetCancelled)),
+                )
+        });
 
         let content: Element<'_, Message> = Container::new(
             Column::new()
                 .align_x(Alignment::Center)
                 .padding(20)
                 .spacing(16)
+                .push_maybe(confirm_row)
+                .push(profile_row)
                 .push(file_row)
                 .push(create_row)
+                .push_maybe(self.job.as_ref().map(|job| job.view(Message::CancelJob)))
                 .push(clear_row)
+                .push(known_words_row)
+                .push(backup_row)
                 .push(
                     Button::new(Text::new("Export to Anki")).on_press(Message::SetExportLocation),
                 ),
@@ -1,54 +1,81 @@
+use std::time::Duration;
+
 use add::{AddTab, Message as AddMessage};
+use dictionary::{DictionaryTab, Message as DictionaryMessage};
 use iced::{
     alignment::{Horizontal, Vertical},
+    time,
     widget::{Column, Container},
-    Element, Length, Task,
+    Element, Length, Subscription, Task, Theme,
 };
 use iced_aw::{TabLabel, Tabs};
 use lemmatize::{LemmatizeTab, Message as LemmatizeMessage};
 use main::{MainTab, Message as MainMessage};
+use settings::{Message as SettingsMessage, SettingsTab};
+use status::{Notification, StatusBar};
+
+use lern::{
+    database::{self, Database},
+    Error,
+};
 
-use crate::{database, error::Error};
+use crate::settings::{Change, Settings, TabBarPosition};
 
 mod add;
+mod dictionary;
+mod entry;
+mod job;
 mod lemmatize;
 mod main;
+mod settings;
+mod status;
 
-const HEADER_SIZE: u16 = 32;
 const TAB_PADDING: u16 = 16;
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
 
-pub fn run() -> iced::Result {
-    iced::application(App::title, App::update, App::view).run_with(App::new)
+/// Opens the window. `settings_error` is shown if the settings couldn't be read.
+pub fn run(db: Database, settings: Settings, settings_error: Option<Error>) -> iced::Result {
+    iced::application(App::title, App::update, App::view)
+        .subscription(App::subscription)
+        .theme(App::theme)
+        .run_with(move || App::new(db, settings, settings_error))
 }
 
 struct App {
+    db: Database,
+    settings: Settings,
     active_tab: TabId,
     add_tab: AddTab,
+    dictionary_tab: DictionaryTab,
     lemmatize_tab: LemmatizeTab,
     main_tab: MainTab,
+    settings_tab: SettingsTab,
+    status_bar: StatusBar,
 }
 
 #[derive(Debug)]
 enum Message {
-    TablesChecked {
-        dictionary: bool,
-        frequency: bool,
-        schedule: bool,
-        queue: bool,
-    },
+    TablesChecked { dictionary: bool, frequency: bool },
     QueueChecked(bool),
+    DatabaseOpened(Database),
     TabSelected(TabId),
     Add(AddMessage),
+    Dictionary(DictionaryMessage),
     Lemmatize(LemmatizeMessage),
     Main(MainMessage),
-    Error(Error),
+    Settings(SettingsMessage),
+    Status(status::Message),
+    ProgressTick,
+    Error(Notification),
 }
 
 #[derive(Clone, PartialEq, Eq, Debug)]
 enum TabId {
     Add,
+    Dictionary,
     Lemmatize,
     Main,
+    Settings,
 }
 
 impl App {
@@ -56,13 +83,27 @@ impl App {
         String::from("Lern")
     }
 
+    fn theme(&self) -> Theme {
+        theme(&self.settings)
+    }
+
+    /// Redraws regularly while an import runs, so its progress bar moves.
+    fn subscription(&self) -> Subscription<Message> {
+        if self.main_tab.is_busy() || self.lemmatize_tab.is_busy() {
+            time::every(PROGRESS_INTERVAL).map(|_| Message::ProgressTick)
+        } else {
+            Subscription::none()
+        }
+    }
+
     fn update(&mut self, message: Message) -> Task<Message> {
         match message {
-            Message::TablesChecked { dictionary, frequency, schedule, queue } => {
+            Message::TablesChecked {
+                dictionary,
+                frequency,
+            } => {
                 self.main_tab.dictionary = dictionary;
                 self.main_tab.frequency = frequency;
-                self.main_tab.schedule = schedule;
-                self.main_tab.queue = queue;
 
                 Task::none()
             }
@@ -70,43 +111,148 @@ impl App {
                 self.add_tab.queue_available = has_items;
                 Task::none()
             }
+            Message::DatabaseOpened(db) => {
+                let (settings, settings_error) =
+                    Settings::load_or_default(&db.profile().settings_path());
+                self.settings = settings;
+                self.main_tab = MainTab::new(db.profile().clone());
+                self.add_tab = AddTab::new(&self.settings);
+                self.dictionary_tab = DictionaryTab::new(&self.settings);
+                self.lemmatize_tab = LemmatizeTab::new(&self.settings);
+                self.settings_tab = SettingsTab::new(&self.settings);
+                self.db = db;
+
+                Task::batch([
+                    Self::check_database(&self.db),
+                    self.notify_settings_error(settings_error),
+                ])
+            }
             Message::TabSelected(selected) => {
+                let task = if self.active_tab == TabId::Settings && selected != TabId::Settings {
+                    let changes = self.settings_tab.finish_edits();
+                    if changes.is_empty() {
+                        Task::none()
+                    } else {
+                        self.change_settings(changes)
+                    }
+                } else {
+                    Task::none()
+                };
                 self.active_tab = selected;
-                Task::none()
+                task
             }
-            Message::Add(message) => match self.add_tab.update(message) {
+            Message::Add(message) => match self.add_tab.update(message, &self.db) {
                 add::Action::None => Task::none(),
                 add::Action::Run(task) => task.map(Message::Add),
+                add::Action::Settings(change) => self.change_settings([change]),
+                add::Action::Notify(notification) => self.notify(notification),
+            },
+            Message::Dictionary(message) => match self.dictionary_tab.update(message, &self.db) {
+                dictionary::Action::None => Task::none(),
+                dictionary::Action::Run(task) => task.map(Message::Dictionary),
+                dictionary::Action::Settings(change) => self.change_settings([change]),
+                dictionary::Action::Notify(notification) => self.notify(notification),
             },
-            Message::Lemmatize(message) => match self.lemmatize_tab.update(message) {
+            Message::Lemmatize(message) => match self.lemmatize_tab.update(message, &self.db) {
                 lemmatize::Action::None => Task::none(),
                 lemmatize::Action::Run(task) => task.map(Message::Lemmatize),
                 lemmatize::Action::Add(task) => task.map(Message::Add),
+                lemmatize::Action::Settings(change) => self.change_settings([change]),
+                lemmatize::Action::Notify(notification) => self.notify(notification),
             },
-            Message::Main(message) => match self.main_tab.update(message) {
+            Message::Main(message) => match self.main_tab.update(message, &self.db) {
                 main::Action::None => Task::none(),
                 main::Action::Run(task) => task.map(Message::Main),
                 main::Action::Add(task) => task.map(Message::Add),
+                main::Action::OpenProfile(profile) => {
+                    Task::perform(Database::open(profile), |res| match res {
+                        Err(e) => {
+                            Message::Error(Notification::error("Opening the profile failed", e))
+                        }
+                        Ok(db) => Message::DatabaseOpened(db),
+                    })
+                }
+                main::Action::Notify(notification) => self.notify(notification),
             },
-            Message::Error(error) => {
-                println!("{}", error);
+            Message::Settings(message) => match self.settings_tab.update(message) {
+                settings::Action::None => Task::none(),
+                settings::Action::Settings(change) => self.change_settings([change]),
+            },
+            Message::ProgressTick => Task::none(),
+            Message::Status(message) => {
+                self.status_bar.update(message);
                 Task::none()
             }
+            Message::Error(notification) => self.notify(notification),
         }
     }
 
     fn view(&self) -> Element<'_, Message> {
-        Tabs::new(Message::TabSelected)
+        let tabs = Tabs::new(Message::TabSelected)
             .push(TabId::Main, self.main_tab.tab_label(), self.main_tab.view())
             .push(TabId::Add, self.add_tab.tab_label(), self.add_tab.view())
+            .push(
+                TabId::Dictionary,
+                self.dictionary_tab.tab_label(),
+                self.dictionary_tab.view(),
+            )
             .push(
                 TabId::Lemmatize,
                 self.lemmatize_tab.tab_label(),
                 self.lemmatize_tab.view(),
             )
+            .push(
+                TabId::Settings,
+                self.settings_tab.tab_label(),
+                self.settings_tab.view(),
+            )
             .set_active_tab(&self.active_tab)
+            .tab_bar_position(match self.settings.tab_bar_position {
+                TabBarPosition::Top => iced_aw::TabBarPosition::Top,
+                TabBarPosition::Bottom => iced_aw::TabBarPosition::Bottom,
+            })
+            .height(Length::Fill);
+
+        Column::new()
+            .push(tabs)
+            .push(self.status_bar.view().map(Message::Status))
             .into()
     }
+
+    fn notify(&mut self, notification: Notification) -> Task<Message> {
+        self.status_bar.push(notification).map(Message::Status)
+    }
+
+    /// Applies changes to the settings, shows them on every tab and saves them to the profile.
+    fn change_settings(&mut self, changes: impl IntoIterator<Item = Change>) -> Task<Message> {
+        for change in changes {
+            self.settings.apply(change);
+        }
+
+        self.add_tab.apply_settings(&self.settings);
+        self.dictionary_tab.apply_settings(&self.settings);
+        self.lemmatize_tab.apply_settings(&self.settings);
+        self.settings_tab.apply_settings(&self.settings);
+
+        let path = self.db.profile().settings_path();
+        Task::future(self.settings.clone().save(path)).then(|res| match res {
+            Err(e) => Task::done(Message::Error(Notification::error(
+                "Saving the settings failed",
+                e,
+            ))),
+            Ok(()) => Task::none(),
+        })
+    }
+}
+
+/// The theme named in the settings, or the default theme.
+fn theme(settings: &Settings) -> Theme {
+    settings
+        .theme
+        .as_ref()
+        .and_then(|name| Theme::ALL.iter().find(|theme| theme.to_string() == *name))
+        .cloned()
+        .unwrap_or_default()
 }
 
 trait Tab {
@@ -135,29 +281,59 @@ trait Tab {
 }
 
 impl App {
-    fn new() -> (Self, Task<Message>) {
-        (
-            Self {
-                active_tab: TabId::Add,
-                add_tab: AddTab::new(),
-                lemmatize_tab: LemmatizeTab::new(),
-                main_tab: MainTab::new(),
-            },
-            Task::batch([
-                Task::future(database::check_tables()).then(|res| match res {
-                    Err(e) => Task::done(Message::Error(e)),
-                    Ok(map) => Task::done(Message::TablesChecked {
-                        dictionary: *map.get("dictionary").unwrap(),
-                        frequency: *map.get("frequency").unwrap(),
-                        schedule: *map.get("cards").unwrap(),
-                        queue: *map.get("lemmas").unwrap(),
-                    }),
+    fn new(
+        db: Database,
+        settings: Settings,
+        settings_error: Option<Error>,
+    ) -> (Self, Task<Message>) {
+        let task = Self::check_database(&db);
+
+        let mut app = Self {
+            active_tab: TabId::Add,
+            add_tab: AddTab::new(&settings),
+            dictionary_tab: DictionaryTab::new(&settings),
+            lemmatize_tab: LemmatizeTab::new(&settings),
+            main_tab: MainTab::new(db.profile().clone()),
+            settings_tab: SettingsTab::new(&settings),
+            status_bar: StatusBar::default(),
+            settings,
+            db,
+        };
+        let settings_task = app.notify_settings_error(settings_error);
+
+        (app, Task::batch([task, settings_task]))
+    }
+
+    fn notify_settings_error(&mut self, error: Option<Error>) -> Task<Message> {
+        match error {
+            Some(e) => self.notify(Notification::error(
+                "Reading the settings failed, the defaults are used",
+                e,
+            )),
+            None => Task::none(),
+        }
+    }
+
+    /// Checks the dictionary and the queue of the opened profile.
+    fn check_database(db: &Database) -> Task<Message> {
+        Task::batch([
+            Task::future(database::check_tables(db.clone())).then(|res| match res {
+                Err(e) => Task::done(Message::Error(Notification::error(
+                    "Checking the dictionary failed",
+                    e,
+                ))),
+                Ok(map) => Task::done(Message::TablesChecked {
+                    dictionary: *map.get("dictionary").unwrap(),
+                    frequency: *map.get("frequency").unwrap(),
                 }),
-                Task::future(database::queue::check_queue()).then(|res| match res {
-                    Err(e) => Task::done(Message::Error(e)),
-                    Ok(has_items) => Task::done(Message::QueueChecked(has_items)),
-                })
-            ]),
-        )
+            }),
+            Task::future(database::queue::check_queue(db.clone())).then(|res| match res {
+                Err(e) => Task::done(Message::Error(Notification::error(
+                    "Checking the queue failed",
+                    e,
+                ))),
+                Ok(has_items) => Task::done(Message::QueueChecked(has_items)),
+            }),
+        ])
     }
 }
@@ -0,0 +1,339 @@
+use iced::{
+    alignment::{Horizontal, Vertical},
+    widget::{Checkbox, Column, Container, PickList, Radio, Row, Slider, Text, TextInput},
+    Alignment, Element, Theme,
+};
+use iced_aw::TabLabel;
+use lern::dictionary::{InputScheme, WordClass};
+
+use crate::settings::{Change, Settings, TabBarPosition};
+
+use super::Tab;
+
+const WORD_CLASSES: [(&str, WordClass); 10] = [
+    ("nouns", WordClass::Noun),
+    ("verbs", WordClass::Verb),
+    ("adjectives", WordClass::Adjective),
+    ("determiners", WordClass::Determiner),
+    ("adverbs", WordClass::Adverb),
+    ("interjections", WordClass::Interjection),
+    ("particles", WordClass::Particle),
+    ("conjunctions", WordClass::Conjunction),
+    ("prepositions", WordClass::Preposition),
+    ("pronouns", WordClass::Pronoun),
+];
+
+pub struct SettingsTab {
+    settings: Settings,
+    max_sentences: String,
+    maximum_interval: String,
+    desired_retention: f64,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Changed(Change),
+    MaxSentencesEdited(String),
+    MaxSentencesSubmitted,
+    MaximumIntervalEdited(String),
+    MaximumIntervalSubmitted,
+    DesiredRetentionMoved(f64),
+    DesiredRetentionReleased,
+}
+
+pub enum Action {
+    None,
+    Settings(Change),
+}
+
+impl SettingsTab {
+    pub fn new(settings: &Settings) -> SettingsTab {
+        SettingsTab {
+            settings: settings.clone(),
+            max_sentences: settings.max_sentences.to_string(),
+            maximum_interval: settings.scheduler.maximum_interval.to_string(),
+            desired_retention: settings.scheduler.desired_retention,
+        }
+    }
+
+    /// Shows settings that were changed somewhere else, keeping unfinished edits.
+    pub fn apply_settings(&mut self, settings: &Settings) {
+        self.settings = settings.clone();
+    }
+
+    /// The changes of edited fields that weren't submitted yet, applied when leaving the tab.
+    /// Invalid edits are discarded.
+    pub fn finish_edits(&mut self) -> Vec<Change> {
+        [self.submit_max_sentences(), self.submit_maximum_interval()]
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+
+    /// The change of the edited number of sentences, the field shows the current value again
+    /// if it's invalid or unchanged.
+    fn submit_max_sentences(&mut self) -> Option<Change> {
+        let change = self.max_sentences_change();
+        if change.is_none() {
+            self.max_sentences = self.settings.max_sentences.to_string();
+        }
+        change
+    }
+
+    fn submit_maximum_interval(&mut self) -> Option<Change> {
+        let change = self.maximum_interval_change();
+        if change.is_none() {
+            self.maximum_interval = self.settings.scheduler.maximum_interval.to_string();
+        }
+        change
+    }
+
+    fn max_sentences_change(&self) -> Option<Change> {
+        self.max_sentences
+            .trim()
+            .parse()
+            .ok()
+            .filter(|&sentences| sentences != self.settings.max_sentences)
+            .map(Change::MaxSentences)
+    }
+
+    fn maximum_interval_change(&self) -> Option<Change> {
+        self.maximum_interval
+            .trim()
+            .parse()
+            .ok()
+            .filter(|&days| days > 0 && days != self.settings.scheduler.maximum_interval)
+            .map(Change::MaximumInterval)
+    }
+
+    pub fn update(&mut self, message: Message) -> Action {
+        match message {
+            Message::Changed(change) => Action::Settings(change),
+            Message::MaxSentencesEdited(value) => {
+                self.max_sentences = value;
+                Action::None
+            }
+            Message::MaxSentencesSubmitted => self
+                .submit_max_sentences()
+                .map_or(Action::None, Action::Settings),
+            Message::MaximumIntervalEdited(value) => {
+                self.maximum_interval = value;
+                Action::None
+            }
+            Message::MaximumIntervalSubmitted => self
+                .submit_maximum_interval()
+                .map_or(Action::None, Action::Settings),
+            Message::DesiredRetentionMoved(value) => {
+                self.desired_retention = value;
+                Action::None
+            }
+            Message::DesiredRetentionReleased => {
+                Action::Settings(Change::DesiredRetention(self.desired_retention))
+            }
+        }
+    }
+}
+
+impl Tab for SettingsTab {
+    type Message = super::Message;
+
+    fn title(&self) -> String {
+        String::from("Settings")
+    }
+
+    fn tab_label(&self) -> TabLabel {
+        TabLabel::Text(self.title())
+    }
+
+    fn content(&self) -> Element<'_, Self::Message> {
+        let theme = super::theme(&self.settings);
+
+        let appearance = Column::new()
+            .spacing(10)
+            .push(Text::new("Appearance").size(20))
+            .push(
+                Row::new()
+                    .align_y(Alignment::Center)
+                    .spacing(16)
+                    .push(Text::new("Theme"))
+                    .push(PickList::new(Theme::ALL, Some(theme), |theme| {
+                        Message::Changed(Change::Theme(theme.to_string()))
+                    })),
+            )
+            .push(
+                TabBarPosition::ALL.iter().fold(
+                    Row::new()
+                        .align_y(Alignment::Center)
+                        .spacing(16)
+                        .push(Text::new("Tab bar")),
+                    |row, &position| {
+                        row.push(
+                            Radio::new(
+                                position,
+                                position,
+                                Some(self.settings.tab_bar_position),
+                                |position| Message::Changed(Change::TabBarPosition(position)),
+                            )
+                            .size(16),
+                        )
+                    },
+                ),
+            );
+
+        let queue = Column::new()
+            .spacing(10)
+            .push(Text::new("Queue").size(20))
+            .push(
+                Row::new()
+                    .spacing(16)
+                    .push(Text::new("Order by"))
+                    .push(
+                        Checkbox::new("frequency in texts", self.settings.order_frequency)
+                            .on_toggle(|value| Message::Changed(Change::OrderFrequency(value))),
+                    )
+                    .push(
+                        Checkbox::new("general frequency", self.settings.order_general_frequency)
+                            .on_toggle(|value| {
+                                Message::Changed(Change::OrderGeneralFrequency(value))
+                            }),
+                    )
+                    .push(
+                        Checkbox::new("first occurence", self.settings.order_first_occurence)
+                            .on_toggle(|value| {
+                                Message::Changed(Change::OrderFirstOccurence(value))
+                            }),
+                    ),
+            )
+            .push(WORD_CLASSES.chunks(5).fold(
+                Column::new().spacing(8).push(Text::new("Include")),
+                |column, chunk| {
+                    column.push(
+                        chunk
+                            .iter()
+                            .fold(Row::new().spacing(16), |row, (label, class)| {
+                                row.push(
+                                    Checkbox::new(
+                                        *label,
+                                        self.settings.word_classes.contains(class),
+                                    )
+                                    .on_toggle(|value| {
+                                        Message::Changed(Change::WordClass(value, class.clone()))
+                                    }),
+                                )
+                            }),
+                    )
+                },
+            ));
+
+        let sentences = Column::new()
+            .spacing(10)
+            .push(Text::new("Sentences").size(20))
+            .push(
+                Checkbox::new(
+                    "Add sentences when lemmatizing",
+                    self.settings.add_sentences,
+                )
+                .on_toggle(|value| Message::Changed(Change::AddSentences(value))),
+            )
+            .push(
+                Row::new()
+                    .align_y(Alignment::Center)
+                    .spacing(16)
+                    .push(Text::new("Sentences shown per word"))
+                    .push(
+                        TextInput::new("10", &self.max_sentences)
+                            .on_input(Message::MaxSentencesEdited)
+                            .on_submit(Message::MaxSentencesSubmitted)
+                            .width(80),
+                    ),
+            );
+
+        let input = Column::new()
+            .spacing(10)
+            .push(Text::new("Russian input").size(20))
+            .push(
+                Checkbox::new(
+                    "Convert Latin letters typed into Russian fields",
+                    self.settings.convert_input,
+                )
+                .on_toggle(|value| Message::Changed(Change::ConvertInput(value))),
+            )
+            .push(
+                Checkbox::new(
+                    "Convert Latin letters typed into the Dictionary search",
+                    self.settings.convert_dictionary_input,
+                )
+                .on_toggle(|value| Message::Changed(Change::ConvertDictionaryInput(value))),
+            )
+            .push(
+                InputScheme::ALL.iter().fold(
+                    Row::new()
+                        .align_y(Alignment::Center)
+                        .spacing(16)
+                        .push(Text::new("Scheme")),
+                    |row, &scheme| {
+                        row.push(
+                            Radio::new(
+                                scheme.to_string(),
+                                scheme,
+                                Some(self.settings.input_scheme),
+                                |scheme| Message::Changed(Change::InputScheme(scheme)),
+                            )
+                            .size(16),
+                        )
+                    },
+                ),
+            );
+
+        let scheduler = Column::new()
+            .spacing(10)
+            .push(Text::new("Scheduler").size(20))
+            .push(
+                Row::new()
+                    .align_y(Alignment::Center)
+                    .spacing(16)
+                    .push(Text::new(format!(
+                        "Desired retention: {:.0}%",
+                        self.desired_retention * 100.0
+                    )))
+                    .push(
+                        Slider::new(
+                            0.7..=0.99,
+                            self.desired_retention,
+                            Message::DesiredRetentionMoved,
+                        )
+                        .step(0.01)
+                        .on_release(Message::DesiredRetentionReleased)
+                        .width(200),
+                    ),
+            )
+            .push(
+                Row::new()
+                    .align_y(Alignment::Center)
+                    .spacing(16)
+                    .push(Text::new("Maximum interval in days"))
+                    .push(
+                        TextInput::new("36500", &self.maximum_interval)
+                            .on_input(Message::MaximumIntervalEdited)
+                            .on_submit(Message::MaximumIntervalSubmitted)
+                            .width(80),
+                    ),
+            );
+
+        let content: Element<'_, Message> = Container::new(
+            Column::new()
+                .padding(20)
+                .spacing(24)
+                .push(appearance)
+                .push(queue)
+                .push(sentences)
+                .push(input)
+                .push(scheduler),
+        )
+        .align_x(Horizontal::Center)
+        .align_y(Vertical::Center)
+        .into();
+
+        content.map(super::Message::Settings)
+    }
+}
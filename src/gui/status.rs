@@ -0,0 +1,163 @@
+use std::{fmt::Display, time::Duration};
+
+use iced::{
+    widget::{container, text, Button, Column, Container, Row, Text},
+    Alignment, Element, Length, Task,
+};
+
+/// Info notifications disappear after this time, errors stay until dismissed.
+const INFO_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Shown at most, older notifications are dropped.
+const MAX_NOTIFICATIONS: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Info,
+    Error,
+}
+
+/// Something the user should know about, e.g. a finished export or a failed database request.
+#[derive(Debug, Clone)]
+pub struct Notification {
+    level: Level,
+    summary: String,
+    details: Option<String>,
+}
+
+impl Notification {
+    pub fn info(summary: impl Into<String>) -> Notification {
+        Notification {
+            level: Level::Info,
+            summary: summary.into(),
+            details: None,
+        }
+    }
+
+    /// Adds details that are shown on request. Info notifications with details stay until
+    /// they're dismissed.
+    pub fn with_details(mut self, details: impl Into<String>) -> Notification {
+        self.details = Some(details.into());
+        self
+    }
+
+    /// An error with a short summary of what failed, the error itself is shown as details.
+    pub fn error(summary: impl Into<String>, error: impl Display) -> Notification {
+        Notification {
+            level: Level::Error,
+            summary: summary.into(),
+            details: Some(error.to_string()),
+        }
+    }
+}
+
+/// The notifications at the bottom of the window. Everything shown here is logged as well.
+#[derive(Default)]
+pub struct StatusBar {
+    notifications: Vec<Entry>,
+    next_id: usize,
+}
+
+struct Entry {
+    id: usize,
+    notification: Notification,
+    expanded: bool,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Dismiss(usize),
+    ToggleDetails(usize),
+}
+
+impl StatusBar {
+    pub fn push(&mut self, notification: Notification) -> Task<Message> {
+        match (&notification.level, &notification.details) {
+            (Level::Error, Some(details)) => log::error!("{}: {details}", notification.summary),
+            (Level::Error, None) => log::error!("{}", notification.summary),
+            (Level::Info, Some(details)) => log::info!("{}: {details}", notification.summary),
+            (Level::Info, None) => log::info!("{}", notification.summary),
+        }
+
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let dismiss_after_timeout =
+            notification.level == Level::Info && notification.details.is_none();
+        self.notifications.push(Entry {
+            id,
+            notification,
+            expanded: false,
+        });
+        if self.notifications.len() > MAX_NOTIFICATIONS {
+            self.notifications.remove(0);
+        }
+
+        if dismiss_after_timeout {
+            Task::perform(tokio::time::sleep(INFO_TIMEOUT), move |_| {
+                Message::Dismiss(id)
+            })
+        } else {
+            Task::none()
+        }
+    }
+
+    pub fn update(&mut self, message: Message) {
+        match message {
+            Message::Dismiss(id) => self.notifications.retain(|entry| entry.id != id),
+            Message::ToggleDetails(id) => {
+                if let Some(entry) = self.notifications.iter_mut().find(|entry| entry.id == id) {
+                    entry.expanded = !entry.expanded;
+                }
+            }
+        }
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        self.notifications
+            .iter()
+            .fold(Column::new().spacing(4).padding(4), |column, entry| {
+                column.push(entry.view())
+            })
+            .into()
+    }
+}
+
+impl Entry {
+    fn view(&self) -> Element<'_, Message> {
+        let notification = &self.notification;
+
+        let summary = match notification.level {
+            Level::Info => Text::new(&notification.summary),
+            Level::Error => Text::new(&notification.summary).style(text::danger),
+        };
+
+        let details_button = notification.details.as_ref().map(|_| {
+            Button::new(Text::new(if self.expanded {
+                "Hide details"
+            } else {
+                "Details"
+            }))
+            .on_press(Message::ToggleDetails(self.id))
+        });
+
+        let row = Row::new()
+            .align_y(Alignment::Center)
+            .spacing(16)
+            .push(summary.width(Length::Fill))
+            .push_maybe(details_button)
+            .push(Button::new(Text::new("Dismiss")).on_press(Message::Dismiss(self.id)));
+
+        let details = notification
+            .details
+            .as_ref()
+            .filter(|_| self.expanded)
+            .map(|details| Text::new(details).size(14));
+
+        Container::new(Column::new().spacing(4).push(row).push_maybe(details))
+            .width(Length::Fill)
+            .padding(8)
+            .style(container::rounded_box)
+            .into()
+    }
+}
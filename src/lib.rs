@@ -0,0 +1,32 @@
+//! Picking Russian vocabulary to learn: dictionary lookup in a wiktionary dump, lemmatization of
+//! texts into a frequency-ordered queue, and spaced repetition scheduling of the resulting cards.
+//!
+//! Everything that touches stored data goes through a [`database::Database`], which is opened
+//! for a [`database::profile::Profile`]:
+//!
+//! ```no_run
+//! use lern::database::{dictionary, profile::{self, Profile}, Database};
+//!
+//! # async fn example() -> lern::Result<()> {
+//! let profile = Profile::new(profile::default_data_dir(), String::from("default"))?;
+//! let db = Database::open(profile).await?;
+//!
+//! lern::dictionary::lemmatize(db.clone(), String::from("Мама мыла раму."), true).await?;
+//! let entries = dictionary::read_entries(db, String::from("мыть")).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The public API follows semantic versioning, breaking changes only happen with a new minor
+//! version as long as the major version is 0.
+
+pub mod database;
+pub mod dictionary;
+pub mod error;
+pub mod fsrs;
+pub mod progress;
+
+pub use error::Error;
+
+/// Result type of all fallible functions in this crate.
+pub type Result<T, E = Error> = std::result::Result<T, E>;
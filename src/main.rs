@@ -1,16 +1,122 @@
 // #![windows_subsystem = "windows"]
-#![allow(dead_code)]
 
-use error::Error;
+use std::{
+    fs::{self, OpenOptions},
+    path::{Path, PathBuf},
+};
 
-mod database;
-mod dictionary;
-mod error;
-mod fsrs;
+use clap::Parser;
+use lern::database::{
+    profile::{self, Profile},
+    Database,
+};
+use settings::Settings;
+use simplelog::{ConfigBuilder, LevelFilter, WriteLogger};
+
+mod cli;
 mod gui;
+mod settings;
+
+/// Name of the log file in the data directory.
+const LOG_FILE: &str = "lern.log";
+/// The log file is moved to `lern.log.old` once it gets bigger than this.
+const MAX_LOG_SIZE: u64 = 1024 * 1024;
 
-pub type Result<T, E = Error> = std::result::Result<T, E>;
+/// Vocabulary trainer for Russian
+#[derive(Parser)]
+#[command(version)]
+struct Args {
+    /// Directory containing the dictionary and all profiles
+    #[arg(long, env = "LERN_DATA_DIR")]
+    data_dir: Option<PathBuf>,
+    /// Profile to open at startup
+    #[arg(long, env = "LERN_PROFILE", default_value = profile::DEFAULT_PROFILE)]
+    profile: String,
+    /// Runs a single command instead of opening the window
+    #[command(subcommand)]
+    command: Option<cli::Command>,
+}
 
 fn main() {
-    gui::run().unwrap();
+    let args = Args::parse();
+
+    let data_dir = args.data_dir.unwrap_or_else(profile::default_data_dir);
+    let profile = match Profile::new(data_dir, args.profile) {
+        Ok(profile) => profile,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(2);
+        }
+    };
+
+    init_logging(profile.data_dir());
+
+    match profile::adopt_legacy_files(&profile) {
+        Ok(true) => log::info!("copied data from ./db to {}", profile.data_dir().display()),
+        Ok(false) => (),
+        Err(e) => log::error!("error copying data from ./db: {e}"),
+    }
+
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    };
+
+    let db = match runtime.block_on(Database::open(profile)) {
+        Ok(db) => db,
+        Err(e) => {
+            log::error!("error opening the database: {e}");
+            eprintln!("error opening the database: {e}");
+            std::process::exit(1);
+        }
+    };
+
+    let (settings, settings_error) = Settings::load_or_default(&db.profile().settings_path());
+
+    match args.command {
+        Some(command) => {
+            if let Some(e) = settings_error {
+                eprintln!("{e}, using the default settings");
+            }
+            if let Err(e) = runtime.block_on(cli::run(db, settings, command)) {
+                log::error!("{e}");
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+        }
+        None => {
+            drop(runtime);
+            if let Err(e) = gui::run(db, settings, settings_error) {
+                log::error!("{e}");
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+/// Logs to a file in the data directory, so errors can be looked up even when the app
+/// wasn't started from a terminal.
+fn init_logging(data_dir: &Path) {
+    let path = data_dir.join(LOG_FILE);
+    if fs::metadata(&path).is_ok_and(|metadata| metadata.len() > MAX_LOG_SIZE) {
+        let _ = fs::rename(&path, path.with_extension("log.old"));
+    }
+
+    let file = fs::create_dir_all(data_dir)
+        .and_then(|()| OpenOptions::new().create(true).append(true).open(&path));
+    let config = ConfigBuilder::new()
+        .add_filter_allow_str("lern")
+        .set_time_format_rfc3339()
+        .build();
+
+    match file {
+        Ok(file) => {
+            let _ = WriteLogger::init(LevelFilter::Info, config, file);
+        }
+        Err(e) => eprintln!("error opening log file {}: {e}", path.display()),
+    }
 }
@@ -0,0 +1,133 @@
+//! Progress reporting and cancellation for long-running jobs.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{error::Error, Result};
+
+/// A handle shared between a running job and whoever shows its progress.
+/// Clones refer to the same job, so one clone can be handed to the job and another one
+/// polled or cancelled from elsewhere.
+///
+/// ```
+/// let progress = lern::progress::Progress::new();
+/// let handle = progress.clone();
+///
+/// handle.cancel();
+/// assert!(progress.is_cancelled());
+/// ```
+#[derive(Debug, Clone)]
+pub struct Progress {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    started: Instant,
+    position: AtomicU64,
+    total: AtomicU64,
+    count: AtomicU64,
+    cancelled: AtomicBool,
+}
+
+impl Default for Progress {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Progress {
+    pub fn new() -> Progress {
+        Progress {
+            inner: Arc::new(Inner {
+                started: Instant::now(),
+                position: AtomicU64::new(0),
+                total: AtomicU64::new(0),
+                count: AtomicU64::new(0),
+                cancelled: AtomicBool::new(false),
+            }),
+        }
+    }
+
+    /// Asks the job to stop. It returns [`Error::Cancelled`] and leaves no partial changes behind.
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Share of the work that is done, `None` as long as the total is unknown.
+    pub fn fraction(&self) -> Option<f32> {
+        let total = self.inner.total.load(Ordering::Relaxed);
+        let position = self.inner.position.load(Ordering::Relaxed);
+
+        (total > 0).then(|| (position.min(total) as f64 / total as f64) as f32)
+    }
+
+    /// Number of items processed so far, e.g. dictionary entries or words.
+    pub fn count(&self) -> u64 {
+        self.inner.count.load(Ordering::Relaxed)
+    }
+
+    /// Estimate of the remaining time, based on the speed so far.
+    pub fn remaining(&self) -> Option<Duration> {
+        let fraction = self.fraction()? as f64;
+        if fraction < 0.01 {
+            return None;
+        }
+
+        let elapsed = self.inner.started.elapsed().as_secs_f64();
+        Some(Duration::from_secs_f64(
+            elapsed * (1.0 - fraction) / fraction,
+        ))
+    }
+
+    /// A short description like "1200 entries, 40%, about 2 min left", with `unit` naming what
+    /// is counted.
+    pub fn describe(&self, unit: &str) -> String {
+        let mut description = format!("{} {unit}", self.count());
+
+        if let Some(fraction) = self.fraction() {
+            description += &format!(", {:.0}%", fraction * 100.0);
+        }
+
+        if let Some(remaining) = self.remaining() {
+            let seconds = remaining.as_secs();
+            if seconds < 60 {
+                description += &format!(", about {seconds} s left");
+            } else {
+                description += &format!(", about {} min left", seconds.div_ceil(60));
+            }
+        }
+
+        description
+    }
+
+    pub(crate) fn set_total(&self, total: u64) {
+        self.inner.total.store(total, Ordering::Relaxed);
+    }
+
+    pub(crate) fn advance(&self, amount: u64) {
+        self.inner.position.fetch_add(amount, Ordering::Relaxed);
+    }
+
+    pub(crate) fn count_item(&self) {
+        self.inner.count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Fails with [`Error::Cancelled`] once [`cancel`](Self::cancel) was called.
+    pub(crate) fn check(&self) -> Result<()> {
+        if self.is_cancelled() {
+            Err(Error::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
@@ -1,11 +1,42 @@
-use crate::{Icon, Message, Tab};
-use iced::{
-    widget::{Column, Container, Radio, Text},
-    Element,
+use std::{collections::HashSet, io, path::Path};
+
+use lern::{
+    dictionary::{InputScheme, WordClass},
+    fsrs::Parameters,
+    Error, Result,
 };
-use iced_aw::style::{tab_bar, StyleFn};
-use iced_aw::tab_bar::TabLabel;
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use tokio::{fs, sync::Mutex};
+
+/// Held while the settings are saved, so saves don't overlap.
+static SAVING: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+/// Settings of a profile, stored as JSON in its directory.
+/// Missing fields fall back to their defaults, so older files keep working.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Name of an iced theme, the default theme if unset or unknown
+    pub theme: Option<String>,
+    pub tab_bar_position: TabBarPosition,
+    pub order_frequency: bool,
+    pub order_general_frequency: bool,
+    pub order_first_occurence: bool,
+    pub word_classes: HashSet<WordClass>,
+    /// Whether lemmatizing stores the sentences of the text
+    pub add_sentences: bool,
+    /// Number of sentences shown for a word on the Add tab
+    pub max_sentences: usize,
+    pub scheduler: Parameters,
+    /// Whether Latin letters typed into Russian fields are converted to Cyrillic
+    pub convert_input: bool,
+    /// The same for the search of the Dictionary tab, which also takes English queries
+    pub convert_dictionary_input: bool,
+    pub input_scheme: InputScheme,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub enum TabBarPosition {
     #[default]
     Top,
@@ -25,130 +56,126 @@ impl From<TabBarPosition> for String {
     }
 }
 
-//#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-#[derive(Default)]
-pub struct TabSettings {
-    pub tab_bar_position: Option<TabBarPosition>,
-    pub tab_bar_theme: Option<usize>,
-    pub tab_bar_theme_id: Option<usize>,
+/// A single change to the settings, made on the Settings tab or anywhere else in the app.
+#[derive(Debug, Clone)]
+pub enum Change {
+    Theme(String),
+    TabBarPosition(TabBarPosition),
+    OrderFrequency(bool),
+    OrderGeneralFrequency(bool),
+    OrderFirstOccurence(bool),
+    WordClass(bool, WordClass),
+    AddSentences(bool),
+    MaxSentences(usize),
+    DesiredRetention(f64),
+    MaximumInterval(usize),
+    ConvertInput(bool),
+    ConvertDictionaryInput(bool),
+    InputScheme(InputScheme),
 }
 
-impl TabSettings {
-    pub fn new() -> Self {
-        TabSettings {
-            tab_bar_position: Some(TabBarPosition::Top),
-            tab_bar_theme: Some(0),
-            tab_bar_theme_id: Some(0),
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            theme: None,
+            tab_bar_position: TabBarPosition::Top,
+            order_frequency: true,
+            order_general_frequency: true,
+            order_first_occurence: true,
+            word_classes: HashSet::from([
+                WordClass::Noun,
+                WordClass::Adjective,
+                WordClass::Adverb,
+                WordClass::Conjunction,
+                WordClass::Determiner,
+                WordClass::Interjection,
+                WordClass::Particle,
+                WordClass::Preposition,
+                WordClass::Pronoun,
+                WordClass::Verb,
+            ]),
+            add_sentences: true,
+            max_sentences: 10,
+            scheduler: Parameters::default(),
+            convert_input: false,
+            convert_dictionary_input: false,
+            input_scheme: InputScheme::Phonetic,
         }
     }
 }
 
-#[derive(Debug, Clone)]
-pub enum SettingsMessage {
-    PositionSelected(TabBarPosition),
-    ThemeSelected(usize),
-}
-
-#[derive(Default)]
-pub struct SettingsTab {
-    settings: TabSettings,
-}
+impl Settings {
+    /// Reads the settings file, the default settings if there is none yet. An invalid file is
+    /// copied to `settings.json.invalid`, so it isn't lost once other settings are saved.
+    pub fn load(path: &Path) -> Result<Settings> {
+        let buffer = match std::fs::read(path) {
+            Ok(buffer) => buffer,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Settings::default()),
+            Err(e) => return Err(Error::ReadFileFailed(path.to_owned(), e)),
+        };
+
+        serde_json::from_slice(&buffer).map_err(|e| {
+            let copy = path.with_extension("json.invalid");
+            match std::fs::copy(path, &copy) {
+                Ok(_) => log::info!("kept the invalid settings as {}", copy.display()),
+                Err(e) => log::error!("error copying the invalid settings: {e}"),
+            }
+            Error::InvalidSettings(path.to_owned(), e)
+        })
+    }
 
-impl SettingsTab {
-    pub fn new() -> Self {
-        SettingsTab {
-            settings: TabSettings::new(),
+    /// Like [`Settings::load`], but falls back to the default settings. The error is logged and
+    /// returned, so it can be shown.
+    pub fn load_or_default(path: &Path) -> (Settings, Option<Error>) {
+        match Settings::load(path) {
+            Ok(settings) => (settings, None),
+            Err(e) => {
+                log::error!("{e}, using the default settings");
+                (Settings::default(), Some(e))
+            }
         }
     }
 
-    pub fn settings(&self) -> &TabSettings {
-        &self.settings
+    /// Writes the settings to a temporary file that then replaces the settings file, so it's
+    /// never left half written.
+    pub async fn save(self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let temporary_path = path.with_extension("json.tmp");
+        let buffer = serde_json::to_vec_pretty(&self)?;
+
+        let _saving = SAVING.lock().await;
+        fs::write(&temporary_path, buffer)
+            .await
+            .map_err(|e| Error::WriteFileFailed(temporary_path.clone(), e))?;
+        fs::rename(&temporary_path, path)
+            .await
+            .map_err(|e| Error::WriteFileFailed(path.to_owned(), e))?;
+
+        Ok(())
     }
 
-    pub fn update(&mut self, message: SettingsMessage) {
-        match message {
-            SettingsMessage::PositionSelected(position) => {
-                self.settings.tab_bar_position = Some(position)
+    pub fn apply(&mut self, change: Change) {
+        match change {
+            Change::Theme(theme) => self.theme = Some(theme),
+            Change::TabBarPosition(position) => self.tab_bar_position = position,
+            Change::OrderFrequency(value) => self.order_frequency = value,
+            Change::OrderGeneralFrequency(value) => self.order_general_frequency = value,
+            Change::OrderFirstOccurence(value) => self.order_first_occurence = value,
+            Change::WordClass(true, class) => {
+                self.word_classes.insert(class);
+            }
+            Change::WordClass(false, class) => {
+                self.word_classes.remove(&class);
             }
-            SettingsMessage::ThemeSelected(index) => {
-                self.settings.tab_bar_theme_id = Some(index);
-                self.settings.tab_bar_theme = Some(index)
+            Change::AddSentences(value) => self.add_sentences = value,
+            Change::MaxSentences(value) => self.max_sentences = value,
+            Change::DesiredRetention(value) => {
+                self.scheduler.desired_retention = value.clamp(0.7, 0.99)
             }
+            Change::MaximumInterval(value) => self.scheduler.maximum_interval = value.max(1),
+            Change::ConvertInput(value) => self.convert_input = value,
+            Change::ConvertDictionaryInput(value) => self.convert_dictionary_input = value,
+            Change::InputScheme(scheme) => self.input_scheme = scheme,
         }
     }
 }
-
-impl Tab for SettingsTab {
-    type Message = Message;
-
-    fn title(&self) -> String {
-        String::from("Settings")
-    }
-
-    fn tab_label(&self) -> TabLabel {
-        //TabLabel::Text(self.title())
-        TabLabel::IconText(Icon::CogAlt.into(), self.title())
-    }
-
-    fn content(&self) -> Element<'_, Self::Message> {
-        let content: Element<'_, SettingsMessage> = Container::new(
-            Column::new()
-                .push(Text::new("TabBar position:").size(20))
-                .push(TabBarPosition::ALL.iter().cloned().fold(
-                    Column::new().padding(10).spacing(10),
-                    |column, position| {
-                        column.push(
-                            Radio::new(
-                                position,
-                                position,
-                                self.settings().tab_bar_position,
-                                SettingsMessage::PositionSelected,
-                            )
-                            .size(16),
-                        )
-                    },
-                ))
-                .push(Text::new("TabBar color:").size(20))
-                .push(
-                    (0..6).fold(Column::new().padding(10).spacing(10), |column, id| {
-                        column.push(
-                            Radio::new(
-                                predefined_style(id),
-                                id,
-                                self.settings().tab_bar_theme_id,
-                                SettingsMessage::ThemeSelected,
-                            )
-                            .size(16),
-                        )
-                    }),
-                ),
-        )
-        .into();
-
-        content.map(Message::Settings)
-    }
-}
-
-fn predefined_style(index: usize) -> String {
-    match index {
-        0 => "Default".to_owned(),
-        1 => "Dark".to_owned(),
-        2 => "Red".to_owned(),
-        3 => "Blue".to_owned(),
-        4 => "Green".to_owned(),
-        5 => "Purple".to_owned(),
-        _ => "Default".to_owned(),
-    }
-}
-
-pub fn style_from_index(index: usize) -> StyleFn<'static, iced::Theme, tab_bar::Style> {
-    match index {
-        0 => Box::new(tab_bar::primary),
-        1 => Box::new(tab_bar::dark),
-        2 => Box::new(tab_bar::red),
-        3 => Box::new(tab_bar::blue),
-        4 => Box::new(tab_bar::green),
-        5 => Box::new(tab_bar::purple),
-        _ => Box::new(tab_bar::primary),
-    }
-}